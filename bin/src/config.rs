@@ -1,11 +1,22 @@
 use std::process::Command;
 use std::time::Duration;
 
+use abar::monitor::Address;
+use abar::output::{Output, XSetRoot};
 use abar::{StatusBar, StatusBarBuilder, StatusBlock, StatusBlockBuilder};
 
-/// abar responds to remote commands over TCP. This defines the port that will
+/// abar responds to remote commands over a Unix socket, which by default lives
+/// at `$XDG_RUNTIME_DIR/abar/default.sock`. This defines the address that will
 /// be used for both sending and receiving instructions.
-pub const PORT: usize = 2227;
+///
+/// To run more than one bar at once, start each one with `--instance NAME`,
+/// and send commands to it the same way.
+///
+/// If you'd rather use TCP, return something like `Address::Tcp(2227)`.
+/// Beware that any user on the machine will then be able to control the bar.
+/// Instances are always Unix sockets, so `--instance` overrides this, and
+/// bars on TCP aren't shown by `--list`.
+pub fn address() -> Address { Address::default() }
 
 /// The number of concurrent worker threads that will be used to evaluate
 /// blocks. If your blocks are all very fast, you can leave this at 1. However,
@@ -13,6 +24,19 @@ pub const PORT: usize = 2227;
 /// might want to bump this up to 2 or 3.
 pub const NUM_WORKERS: usize = 2;
 
+/// Where the bar should be drawn. The default works well for dwm, but other
+/// setups might require printing to stdout (abar::output::Stdout), etc.
+///
+/// If you enable abar's "x11" feature in Cargo.toml, XRootName can be used in
+/// place of XSetRoot to avoid spawning a new process on every redraw.
+///
+/// To draw to several places at once, combine them with a Fanout, eg.
+///
+/// Fanout::new()
+///     .output(XSetRoot::default())
+///     .output(TextFile::new("/tmp/abar.json").format(Json).append())
+pub fn output() -> impl Output { XSetRoot::default() }
+
 /// Define your StatusBar here. You probably just want to set the delimiters, or
 /// set hide_empty_modules.
 pub fn bar() -> StatusBar {
@@ -29,6 +53,7 @@ pub fn bar() -> StatusBar {
 /// String, and an optional update interval. If you haven't used Rust much
 /// before, I'd recommend copying the example syntax.
 fn blocks() -> Vec<StatusBlock> {
+    use abar::signals::rtmin;
     use abar::utils::run;
 
     // You can use this wrapper to invoke shell commands.
@@ -38,16 +63,20 @@ fn blocks() -> Vec<StatusBlock> {
         .min_size(8)
         .build();
 
-    // Alternatively, you can use the built-in methods,
+    // Alternatively, you can use the built-in methods. Blocks can also be
+    // updated by a signal, eg. `pkill -RTMIN+1 basic-usage`.
     let shell_example = StatusBlockBuilder::default()
         .name("shell_example")
         .function(shell_example)
         .update_interval(Duration::from_secs(1))
+        .signal(rtmin(1))
         .build();
 
-    // or use vanilla Rust exclusively.
+    // or use vanilla Rust exclusively. Tags let commands act on several blocks
+    // at once, eg. `pause tag:busy`.
     let vanilla_example = StatusBlockBuilder::default()
         .name("vanilla_example")
+        .tag("busy")
         .function(rand_example)
         .update_interval(Duration::from_millis(50))
         .size(6)
@@ -1,8 +1,10 @@
-use std::env;
-use std::io::Write;
-use std::net::TcpStream;
+use std::path::PathBuf;
+use std::{env, fs, io};
 
 use getopts::Options;
+use serde_json::Value;
+
+use crate::monitor::{self, Address, Client, Reply, Request};
 
 /// Prints usage information for the CLI interface, generated by getopts.
 fn print_usage(program: &str, opts: Options) {
@@ -12,19 +14,28 @@ fn print_usage(program: &str, opts: Options) {
 
 /// Parses arguments from the environment and reacts accordingly. Known flags
 /// will be handled here, and all other inputs will be forwarded to the
-/// currently running bar over TCP.
-pub fn process_args() {
+/// currently running bar at the given Address (unless overridden by the
+/// `--instance`, `--socket` or `--port` flags).
+///
+/// If there's nothing to send, this returns the Address that the new bar
+/// should listen on.
+pub fn process_args(address: &Address) -> Address {
     let args: Vec<String> = env::args().collect();
 
     // If there were no arguments, simply return.
     if args.len() <= 1 {
-        return;
+        return address.clone();
     }
 
     // Otherwise, define a new set of potential arguments,
     let program = args[0].clone();
     let mut opts = Options::new();
     opts.optflag("h", "help", "print this help menu");
+    opts.optflag("l", "list", "list the running instances");
+    opts.optopt("i", "instance", "use the named instance's socket", "NAME");
+    opts.optflag("j", "json", "print replies as raw JSON");
+    opts.optopt("s", "socket", "send commands to a Unix socket", "PATH");
+    opts.optopt("p", "port", "send commands to a TCP port", "PORT");
 
     // get matches for those arguments,
     let matches = match opts.parse(&args[1..]) {
@@ -40,15 +51,189 @@ pub fn process_args() {
         std::process::exit(0);
     }
 
-    // Finally, if the program is still executing, forward any args to the
-    // currently running bar (if it exists).
-    if let Ok(mut stream) = TcpStream::connect("127.0.0.1:2227") {
-        let data = args[1..].join(" ");
-        stream.write_all(data.as_bytes()).unwrap();
+    if matches.opt_present("l") {
+        for name in monitor::instances() {
+            println!("{}", name);
+        }
         std::process::exit(0);
     }
-    else {
-        eprintln!("Could not connect to a running bar instance.");
+
+    // Instances are Unix sockets, so they can't be mixed with a port.
+    if matches.opt_present("i") && matches.opt_present("p") {
+        eprintln!("--instance and --port can't be used together");
         std::process::exit(1);
     }
+
+    let address = if let Some(name) = matches.opt_str("i") {
+        match Address::instance(&name) {
+            Ok(address) => address,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            },
+        }
+    }
+    else if let Some(path) = matches.opt_str("s") {
+        Address::Unix(PathBuf::from(path))
+    }
+    else if let Some(port) = matches.opt_str("p") {
+        match port.parse() {
+            Ok(port) => Address::Tcp(port),
+            Err(_) => {
+                eprintln!("Invalid port: {}", port);
+                std::process::exit(1);
+            },
+        }
+    }
+    else {
+        address.clone()
+    };
+
+    // If there's nothing left to send, the bar itself should be started.
+    if matches.free.is_empty() {
+        return address;
+    }
+
+    // Batches are a file of commands, which are all sent in one go.
+    if matches.free[0] == "batch" {
+        match &matches.free[1..] {
+            [path] => run_batch(path, &address, matches.opt_present("j")),
+            _ => {
+                eprintln!("usage: batch FILE");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    // Finally, if the program is still executing, forward any args to the
+    // currently running bar (if it exists), and report its reply.
+    // Most commands get a single reply, but subscriptions keep going until
+    // either side hangs up.
+    let request = Request::new(&matches.free[0], &matches.free[1..]);
+    let streaming = request.command == "subscribe";
+    let replies = match request.stream(&address) {
+        Ok(replies) => replies,
+        Err(err) => {
+            eprintln!("Could not reach a running bar at {}: {}", address, err);
+            std::process::exit(1);
+        },
+    };
+
+    for reply in replies {
+        match reply {
+            Ok(reply) => {
+                print_reply(&reply, matches.opt_present("j"), !streaming);
+                if !reply.is_ok() {
+                    std::process::exit(1);
+                }
+                else if !streaming {
+                    break;
+                }
+            },
+            Err(err) => {
+                eprintln!("Lost connection to the bar: {}", err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    std::process::exit(0);
+}
+
+/// Sends each command in the file (or stdin, if the path is "-") to the bar at
+/// the given Address, in order and over a single connection. Commands are
+/// written one per line, just as they would be on the command line.
+///
+/// Blank lines and comments are skipped. Any errors are reported along with
+/// the line that caused them, but don't stop the rest of the file from
+/// running.
+fn run_batch(path: &str, address: &Address, json: bool) -> ! {
+    let script = if path == "-" {
+        io::read_to_string(io::stdin())
+    }
+    else {
+        fs::read_to_string(path)
+    };
+
+    let script = match script {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", path, err);
+            std::process::exit(1);
+        },
+    };
+
+    let mut client = match Client::connect(address) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("Could not reach a running bar at {}: {}", address, err);
+            std::process::exit(1);
+        },
+    };
+
+    let mut failed = false;
+    for (number, line) in script.lines().enumerate() {
+        let fail = |message: &str| {
+            eprintln!("{}:{}: error: {}", path, number + 1, message);
+        };
+
+        let request = match Request::parse(line) {
+            Ok(Some(request)) if request.command == "subscribe" => {
+                fail("subscribe can't be used in a batch");
+                failed = true;
+                continue;
+            },
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(message) => {
+                fail(&message);
+                failed = true;
+                continue;
+            },
+        };
+
+        match client.send(&request) {
+            Ok(reply) if !reply.is_ok() && !json => {
+                fail(reply.error.as_deref().unwrap_or_default());
+                failed = true;
+            },
+            Ok(reply) => {
+                failed |= !reply.is_ok();
+                print_reply(&reply, json, true);
+            },
+            Err(err) => {
+                eprintln!("Lost connection to the bar: {}", err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    std::process::exit(if failed { 1 } else { 0 });
+}
+
+/// Prints the Reply from a bar. Errors are printed to stderr, and any data is
+/// printed to stdout (as-is if it's a string or a list of strings, or as JSON
+/// otherwise). Streamed replies are kept to one line each.
+fn print_reply(reply: &Reply, json: bool, pretty: bool) {
+    if json {
+        println!("{}", serde_json::to_string(reply).unwrap());
+        return;
+    }
+
+    if let Some(error) = &reply.error {
+        eprintln!("error: {}", error);
+    }
+
+    match &reply.data {
+        Value::Null => (),
+        Value::String(data) => println!("{}", data),
+        Value::Array(items) if items.iter().all(Value::is_string) => {
+            for item in items.iter().filter_map(Value::as_str) {
+                println!("{}", item);
+            }
+        },
+        data if pretty =>
+            println!("{}", serde_json::to_string_pretty(data).unwrap()),
+        data => println!("{}", data),
+    }
 }
@@ -1,12 +1,23 @@
 pub mod cli;
 pub mod monitor;
+pub mod output;
+pub mod signals;
 mod statusbar;
 mod statusblock;
+pub mod style;
+#[cfg(test)]
+mod test_utils;
 pub mod threadpool;
 pub mod utils;
 
-pub use statusbar::{StatusBar, StatusBarBuilder};
-pub use statusblock::{StatusBlock, StatusBlockBuilder};
+pub use statusbar::{
+    Alignment, Layout, LayoutError, Sections, Segment, SegmentKind, StatusBar,
+    StatusBarBuilder, Token,
+};
+pub use statusblock::{
+    BlockGroup, BlockGroupBuilder, BlockInfo, Blocks, Change, Hook, Selector,
+    State, StatusBlock, StatusBlockBuilder, Threshold,
+};
 
 #[cfg(test)]
 mod tests {}
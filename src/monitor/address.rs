@@ -0,0 +1,333 @@
+use std::fmt;
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where a Monitor listens for commands, and where the CLI sends them.
+///
+/// By default, commands are sent over a Unix socket that only the current user
+/// can connect to. TCP can be used instead, but be aware that anyone on the
+/// machine will be able to control the bar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Address {
+    Unix(PathBuf),
+    Tcp(u16),
+}
+
+/// A bound Address, waiting for connections.
+pub(super) enum Listener {
+    Unix(UnixListener, SocketFile),
+    Tcp(TcpListener),
+}
+
+/// A Unix socket that this process is listening on, which is removed as soon
+/// as any copy of it is dropped. Later copies leave it alone, as another bar
+/// may have taken its place by then.
+#[derive(Clone, Debug)]
+pub struct SocketFile {
+    path:    PathBuf,
+    removed: Arc<AtomicBool>,
+}
+
+/// A connection to a Monitor, over either a Unix socket or TCP.
+pub enum Connection {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Address {
+    /// Returns the socket used by the named instance of the bar, ie.
+    /// `$XDG_RUNTIME_DIR/abar/<name>.sock`. Each instance needs its own
+    /// socket, so this should be used to run more than one bar at once.
+    ///
+    /// Instances are always Unix sockets, and have no TCP equivalent. Bars
+    /// listening on TCP are told apart by their port alone, and aren't listed
+    /// by instances().
+    ///
+    /// Names can't be empty, or contain `/`, `..` or NUL, as they'd escape
+    /// the socket directory.
+    pub fn instance(name: &str) -> io::Result<Self> {
+        if name.is_empty() || name.contains(['/', '\0']) || name.contains("..")
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid instance name '{}'", name),
+            ));
+        }
+
+        Ok(Address::Unix(socket_dir().join(format!("{}.sock", name))))
+    }
+
+    /// Connects to a running Monitor.
+    pub fn connect(&self) -> io::Result<Connection> {
+        match self {
+            Address::Unix(path) =>
+                UnixStream::connect(path).map(Connection::Unix),
+            Address::Tcp(port) =>
+                TcpStream::connect(("127.0.0.1", *port)).map(Connection::Tcp),
+        }
+    }
+
+    /// Starts listening on the Address. Unix sockets are only accessible to
+    /// the current user, and stale sockets left behind by a bar that's no
+    /// longer running are replaced.
+    pub(super) fn bind(&self) -> io::Result<Listener> {
+        match self {
+            Address::Unix(path) => {
+                let listener = bind_unix(path)?;
+                let file = SocketFile {
+                    path:    path.clone(),
+                    removed: Arc::new(AtomicBool::new(false)),
+                };
+                Ok(Listener::Unix(listener, file))
+            },
+            Address::Tcp(port) =>
+                TcpListener::bind(("127.0.0.1", *port)).map(Listener::Tcp),
+        }
+    }
+}
+
+/// The default Address is the socket for the "default" instance.
+impl Default for Address {
+    fn default() -> Self {
+        Self::instance("default").expect("\"default\" is a valid name")
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Unix(path) => write!(f, "{}", path.display()),
+            Address::Tcp(port) => write!(f, "127.0.0.1:{}", port),
+        }
+    }
+}
+
+/// Returns the directory that sockets are kept in. This is
+/// `$XDG_RUNTIME_DIR/abar`, or `/tmp/abar-<uid>` if XDG_RUNTIME_DIR isn't set.
+pub fn socket_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("abar"),
+        _ => {
+            // SAFETY: getuid has no preconditions, and can't fail.
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("abar-{}", uid))
+        },
+    }
+}
+
+/// Returns the names of every instance that's currently running, in
+/// alphabetical order.
+pub fn instances() -> Vec<String> { instances_in(&socket_dir()) }
+
+fn instances_in(dir: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sock"))
+        .filter(|path| UnixStream::connect(path).is_ok())
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+
+    names.sort();
+    names
+}
+
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+
+    if path.exists() {
+        // If something's still listening, then another bar is running.
+        // Otherwise, the socket was left behind and can be replaced.
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another bar is already running (use --instance to run more \
+                 than one)",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+
+    Ok(listener)
+}
+
+/// Creates a directory that only the current user can access. If it already
+/// exists, it must be owned by the current user.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    // SAFETY: getuid has no preconditions, and can't fail.
+    let uid = unsafe { libc::getuid() };
+    if fs::metadata(dir)?.uid() != uid {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is owned by another user", dir.display()),
+        ));
+    }
+
+    fs::set_permissions(dir, Permissions::from_mode(0o700))
+}
+
+impl Listener {
+    pub(super) fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Listener::Unix(listener, _) =>
+                listener.accept().map(|(stream, _)| Connection::Unix(stream)),
+            Listener::Tcp(listener) =>
+                listener.accept().map(|(stream, _)| Connection::Tcp(stream)),
+        }
+    }
+}
+
+impl Listener {
+    pub(super) fn socket_file(&self) -> Option<SocketFile> {
+        match self {
+            Listener::Unix(_, file) => Some(file.clone()),
+            Listener::Tcp(_) => None,
+        }
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if !self.removed.swap(true, Ordering::SeqCst) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Connection {
+    pub fn set_read_timeout(
+        &self, timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        match self {
+            Connection::Unix(stream) => stream.set_read_timeout(timeout),
+            Connection::Tcp(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(
+        &self, timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        match self {
+            Connection::Unix(stream) => stream.set_write_timeout(timeout),
+            Connection::Tcp(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Connection::Unix(stream) =>
+                stream.try_clone().map(Connection::Unix),
+            Connection::Tcp(stream) => stream.try_clone().map(Connection::Tcp),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Unix(stream) => stream.read(buf),
+            Connection::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Unix(stream) => stream.write(buf),
+            Connection::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Unix(stream) => stream.flush(),
+            Connection::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    #[test]
+    fn unix_sockets_are_private() {
+        let path = temp_path("private").join("test.sock");
+        let _listener = Address::Unix(path.clone()).bind().unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+    }
+
+    #[test]
+    fn instance_names_stay_in_the_socket_dir() {
+        let path = Address::instance("work").unwrap();
+        assert_eq!(path, Address::Unix(socket_dir().join("work.sock")));
+
+        for name in ["", "a/b", "../x", "..", "a\0b"] {
+            assert!(Address::instance(name).is_err());
+        }
+    }
+
+    #[test]
+    fn running_instances_are_listed() {
+        let dir = temp_path("instances");
+
+        let _a = Address::Unix(dir.join("a.sock")).bind().unwrap();
+        let _b = Address::Unix(dir.join("b.sock")).bind().unwrap();
+        drop(UnixListener::bind(dir.join("stale.sock")).unwrap());
+
+        assert_eq!(instances_in(&dir), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn stale_sockets_are_replaced() {
+        let path = temp_path("stale").join("test.sock");
+        let address = Address::Unix(path.clone());
+        create_private_dir(path.parent().unwrap()).unwrap();
+
+        let listener = UnixListener::bind(&path).unwrap();
+        assert!(address.bind().is_err());
+
+        drop(listener);
+        assert!(address.bind().is_ok());
+    }
+
+    #[test]
+    fn sockets_are_removed_when_dropped() {
+        let path = temp_path("removed").join("test.sock");
+        let address = Address::Unix(path.clone());
+
+        let listener = address.bind().unwrap();
+        let file = listener.socket_file();
+        drop(listener);
+        assert!(!path.exists());
+
+        // Sockets that have been replaced since are left alone.
+        let _listener = address.bind().unwrap();
+        drop(file);
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,535 @@
+mod address;
+mod protocol;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub use address::{instances, socket_dir, Address, Connection, SocketFile};
+use address::Listener;
+pub use protocol::{Client, Reply, Request, Status, PROTOCOL_VERSION};
+
+use crate::statusblock::Selector;
+
+pub type MonitorSender = mpsc::SyncSender<Command>;
+pub type MonitorReceiver = mpsc::Receiver<Command>;
+pub type ReplySender = mpsc::SyncSender<Reply>;
+pub type ReplyReceiver = mpsc::Receiver<Reply>;
+
+/// How many Replies can be waiting to be sent to a client. Subscribers that
+/// fall further behind than this are dropped.
+pub const REPLY_BUFFER: usize = 64;
+
+/// Represents a command that can be sent to the meta framework.
+pub enum Command {
+    Refresh,
+    Shutdown,
+    Update(Vec<Selector>),
+    /// Updates any blocks that are bound to the given Unix signal.
+    Signal(i32),
+    /// Stops the selected blocks from updating on their timers.
+    Pause(Vec<Selector>),
+    /// Restarts the timers of the selected blocks.
+    Resume(Vec<Selector>),
+    /// Changes how often the selected blocks update, or stops them from
+    /// updating on a timer if the interval is None.
+    Interval {
+        selector: Selector,
+        interval: Option<Duration>,
+    },
+    /// Gets the text of the whole bar, or of the selected blocks.
+    Get(Option<Selector>),
+    /// Lists the names of every block in the bar.
+    List,
+    /// Describes the state of the selected blocks.
+    Inspect(Selector),
+    /// Overwrites the text of the selected blocks, optionally until the given
+    /// TTL has passed.
+    Set {
+        selector: Selector,
+        text:     String,
+        ttl:      Option<Duration>,
+    },
+    /// Streams the bar to whoever sent the Request, with one Reply for each
+    /// new frame (or block change).
+    Subscribe(Subscription),
+    /// Runs the given command, and sends a Reply describing the result.
+    Request {
+        command: Box<Command>,
+        reply:   ReplySender,
+    },
+}
+
+/// What a subscriber is sent whenever the bar changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subscription {
+    /// The whole bar, as plain text.
+    Text,
+    /// The whole Frame, as JSON.
+    Json,
+    /// A Change for each named block whose output or State has changed.
+    Changes,
+}
+
+/// A struct for monitoring and reacting to external input, over either a Unix
+/// socket or TCP.
+///
+/// Connections send Requests one line at a time, and are sent a Reply once
+/// the bar has acted on each one. Subscriptions are the exception, and keep
+/// being sent Replies until either side hangs up. See the protocol module for
+/// details. Connections that stay idle for a minute are closed.
+///
+/// Connections are handled on their own threads, so a slow (or malicious)
+/// client can't hold up anyone else. Clients that take too long or send too
+/// much are disconnected, and any errors are logged rather than stopping the
+/// Monitor.
+pub struct Monitor {
+    listener: Listener,
+    tx:       MonitorSender,
+}
+
+/// The longest that a client can take to send the whole of a Request once
+/// it's started, or to accept each part of a Reply.
+#[cfg(not(test))]
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(test)]
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// The longest that a connection can go between Requests before it's closed.
+/// Blank lines and comments don't count as Requests.
+#[cfg(not(test))]
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+#[cfg(test)]
+const IDLE_TIMEOUT: Duration = Duration::from_millis(600);
+
+/// The longest that the bar can take to reply to a Request.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest Request that will be accepted, in bytes.
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+/// The number of connections that can be handled at once, not counting
+/// subscriptions. Any more than this are turned away.
+const MAX_CONNECTIONS: usize = 64;
+
+/// The number of subscriptions that can be open at once. These are counted
+/// separately, so that subscribers can't lock out other commands.
+const MAX_SUBSCRIPTIONS: usize = 64;
+
+impl Monitor {
+    /// Try to listen on the given Address and return a new Monitor.
+    pub fn new(tx: MonitorSender, address: &Address) -> Self {
+        match address.bind() {
+            Ok(listener) => Self { listener, tx },
+            Err(err) => {
+                eprintln!("Could not listen on {}: {}", address, err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    /// Returns the Unix socket that the Monitor is listening on, if any. The
+    /// socket is removed once either this or the Monitor is dropped, so it can
+    /// be held on to when the Monitor won't be (eg. while it runs on another
+    /// thread).
+    pub fn socket_file(&self) -> Option<SocketFile> {
+        self.listener.socket_file()
+    }
+
+    /// Listen for incoming connections, pipe any received commands into
+    /// self.tx, and reply once they've been handled.
+    pub fn run(self) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let subscriptions = Arc::new(AtomicUsize::new(0));
+
+        loop {
+            let mut connection = match self.listener.accept() {
+                Ok(connection) => connection,
+                Err(err) => {
+                    // Errors here are usually temporary (eg. running out of
+                    // file descriptors), so back off for a moment and retry.
+                    eprintln!("Could not accept a connection: {}", err);
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                },
+            };
+
+            let slot = match Slot::take(&connections, MAX_CONNECTIONS) {
+                Some(slot) => slot,
+                None => {
+                    let reply = Reply::error("too many connections");
+                    let _ = write_reply(&mut connection, &reply);
+                    continue;
+                },
+            };
+
+            let tx = self.tx.clone();
+            let subscriptions = subscriptions.clone();
+
+            thread::spawn(move || {
+                match handle(&tx, connection, slot, &subscriptions) {
+                    Err(err) if !is_disconnect(&err) =>
+                        eprintln!("Could not respond to a command: {}", err),
+                    _ => (),
+                }
+            });
+        }
+    }
+}
+
+/// Reads Requests from the connection until the client hangs up, forwards
+/// each one to the bar, and sends back the bar's Reply (or Replies).
+fn handle(
+    tx: &MonitorSender, mut connection: Connection, slot: Slot,
+    subscriptions: &Arc<AtomicUsize>,
+) -> io::Result<()> {
+    connection.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(connection.try_clone()?);
+    let mut line = Vec::new();
+    let mut idle_until = Instant::now() + IDLE_TIMEOUT;
+
+    loop {
+        // Wait for the next Request to start, and then give the client a
+        // shorter time to finish it.
+        if !wait_for_data(&mut reader, idle_until)? {
+            return Ok(());
+        }
+
+        line.clear();
+        let deadline = Instant::now() + CLIENT_TIMEOUT;
+        read_line(&mut reader, &mut line, deadline)?;
+
+        if line.len() as u64 > MAX_REQUEST_SIZE {
+            // There's no telling where the next Request starts, so give up on
+            // the connection.
+            eprintln!("Ignoring a request that was too large");
+            let reply = Reply::error("request too large");
+            return write_reply(&mut connection, &reply);
+        }
+
+        let command = match Request::parse(&String::from_utf8_lossy(&line)) {
+            Ok(Some(request)) => request.to_command(),
+            Ok(None) => continue,
+            Err(message) => Err(message),
+        };
+
+        let command = match command {
+            Ok(command) => command,
+            Err(message) => {
+                eprintln!("Ignoring a bad request: {}", message);
+                write_reply(&mut connection, &Reply::error(&message))?;
+                continue;
+            },
+        };
+
+        if let Command::Subscribe(_) = command {
+            return subscribe(tx, command, connection, slot, subscriptions);
+        }
+
+        let reply = match forward(tx, command) {
+            Ok(replies) => replies.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(
+                |_| Reply::error("the bar did not reply in time"),
+            ),
+            Err(reply) => reply,
+        };
+        write_reply(&mut connection, &reply)?;
+
+        idle_until = Instant::now() + IDLE_TIMEOUT;
+    }
+}
+
+/// Reads up to (and including) the next newline into `line`, unless the
+/// client hangs up first. Gives up once the line is longer than
+/// MAX_REQUEST_SIZE.
+fn read_line(
+    reader: &mut BufReader<Connection>, line: &mut Vec<u8>, deadline: Instant,
+) -> io::Result<()> {
+    while line.len() as u64 <= MAX_REQUEST_SIZE {
+        if !wait_for_data(reader, deadline)? {
+            break;
+        }
+
+        let buf = reader.buffer();
+        let (used, done) = match buf.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buf.len(), false),
+        };
+
+        line.extend_from_slice(&buf[..used]);
+        reader.consume(used);
+
+        if done {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits until there's something in the reader's buffer, failing with
+/// TimedOut if nothing arrives before the deadline. Returns false if the
+/// client hung up instead.
+fn wait_for_data(
+    reader: &mut BufReader<Connection>, deadline: Instant,
+) -> io::Result<bool> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        reader.get_ref().set_read_timeout(Some(remaining))?;
+
+        match reader.fill_buf() {
+            Ok(buf) => return Ok(!buf.is_empty()),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Hands the connection over to a subscription, which keeps going until the
+/// bar drops it or the client hangs up. Either way, there's no need to report
+/// it.
+///
+/// Subscriptions give up their connection slot in exchange for a subscription
+/// slot.
+fn subscribe(
+    tx: &MonitorSender, command: Command, mut connection: Connection,
+    slot: Slot, subscriptions: &Arc<AtomicUsize>,
+) -> io::Result<()> {
+    let _subscription = match Slot::take(subscriptions, MAX_SUBSCRIPTIONS) {
+        Some(subscription) => subscription,
+        None => {
+            let reply = Reply::error("too many subscriptions");
+            return write_reply(&mut connection, &reply);
+        },
+    };
+    drop(slot);
+
+    let replies = match forward(tx, command) {
+        Ok(replies) => replies,
+        Err(reply) => return write_reply(&mut connection, &reply),
+    };
+
+    for reply in replies {
+        if write_reply(&mut connection, &reply).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the command to the bar, and returns the channel that it will reply
+/// on.
+fn forward(
+    tx: &MonitorSender, command: Command,
+) -> Result<ReplyReceiver, Reply> {
+    let (reply_tx, reply_rx) = mpsc::sync_channel(REPLY_BUFFER);
+    let command = Command::Request {
+        command: Box::new(command),
+        reply:   reply_tx,
+    };
+
+    match tx.send(command) {
+        Ok(()) => Ok(reply_rx),
+        Err(_) => Err(Reply::error("the bar has stopped running")),
+    }
+}
+
+/// Whether the error just means that the client went away, or took too long.
+fn is_disconnect(err: &io::Error) -> bool {
+    use io::ErrorKind::*;
+
+    matches!(
+        err.kind(),
+        WouldBlock | TimedOut | UnexpectedEof | BrokenPipe | ConnectionReset
+    )
+}
+
+/// A place within one of the Monitor's limits, which is given back when it's
+/// dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Takes a place from the count, unless it's already at the limit.
+    fn take(count: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        if count.fetch_add(1, Ordering::SeqCst) >= limit {
+            count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(Self(count.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::SeqCst); }
+}
+
+fn write_reply(connection: &mut Connection, reply: &Reply) -> io::Result<()> {
+    writeln!(connection, "{}", serde_json::to_string(reply)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    /// Starts a Monitor on a new socket, with a stand-in for the bar that only
+    /// knows about the "cpu" block. Subscribers are sent one Reply, and then
+    /// kept waiting.
+    fn monitor(name: &str) -> Address {
+        let address = Address::Unix(temp_path(name).join("test.sock"));
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        let monitor = Monitor::new(tx, &address);
+        thread::spawn(move || monitor.run());
+
+        thread::spawn(move || {
+            let mut subscribers = Vec::new();
+
+            for command in rx {
+                if let Command::Request { command, reply } = command {
+                    if let Command::Subscribe(_) = *command {
+                        let _ = reply.send(Reply::ok(Value::Null));
+                        subscribers.push(reply);
+                        continue;
+                    }
+
+                    let _ = reply.send(match *command {
+                        Command::Update(selectors)
+                            if selectors == [Selector::parse("cpu")] =>
+                            Reply::ok(Value::from("updated")),
+                        _ => Reply::error("no such block"),
+                    });
+                }
+            }
+        });
+
+        address
+    }
+
+    fn update(address: &Address, name: &str) -> Reply {
+        Request::new("update", &[String::from(name)]).send(address).unwrap()
+    }
+
+    #[test]
+    fn requests_are_answered_by_the_bar() {
+        let address = monitor("answers");
+
+        assert_eq!(update(&address, "cpu"), Reply::ok(Value::from("updated")));
+        assert_eq!(update(&address, "gpu"), Reply::error("no such block"));
+
+        let reply = Request::new("explode", &[]).send(&address).unwrap();
+        assert_eq!(reply, Reply::error("unknown command 'explode'"));
+    }
+
+    #[test]
+    fn connections_can_send_several_requests() {
+        let address = monitor("several");
+
+        let mut connection = address.connect().unwrap();
+        write!(connection, "update cpu\n\n# nothing\nupdate 'g pu'\n").unwrap();
+
+        let replies: Vec<Reply> = BufReader::new(connection)
+            .lines()
+            .take(2)
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(replies, [
+            Reply::ok(Value::from("updated")),
+            Reply::error("no such block"),
+        ]);
+
+        let mut client = Client::connect(&address).unwrap();
+        for name in ["cpu", "cpu"] {
+            let request = Request::new("update", &[String::from(name)]);
+            assert!(client.send(&request).unwrap().is_ok());
+        }
+    }
+
+    #[test]
+    fn idle_clients_do_not_block_others() {
+        let address = monitor("idle");
+
+        // Connect, but never send anything.
+        let _idle = address.connect().unwrap();
+
+        assert_eq!(update(&address, "cpu"), Reply::ok(Value::from("updated")));
+    }
+
+    #[test]
+    fn subscribers_do_not_use_up_connections() {
+        let address = monitor("subscribers");
+
+        let subscribers: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| {
+                let request = Request::new("subscribe", &[]);
+                let mut replies = request.stream(&address).unwrap();
+                assert!(replies.next().unwrap().unwrap().is_ok());
+                replies
+            })
+            .collect();
+
+        assert_eq!(update(&address, "cpu"), Reply::ok(Value::from("updated")));
+        drop(subscribers);
+    }
+
+    /// Sends the text one byte at a time, with the given delay between each,
+    /// until the connection is closed. Returns how long that took.
+    fn drip(address: &Address, text: &str, delay: Duration) -> Duration {
+        let start = Instant::now();
+        let mut connection = address.connect().unwrap();
+
+        for byte in text.bytes().cycle() {
+            if connection.write_all(&[byte]).is_err() {
+                break;
+            }
+            thread::sleep(delay);
+        }
+
+        start.elapsed()
+    }
+
+    #[test]
+    fn slow_requests_are_dropped() {
+        let address = monitor("slow");
+        let delay = CLIENT_TIMEOUT / 3;
+
+        let elapsed = drip(&address, "update cpu", delay);
+        assert!(elapsed < CLIENT_TIMEOUT * 3, "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn blank_lines_do_not_keep_connections_open() {
+        let address = monitor("blank");
+        let delay = IDLE_TIMEOUT / 6;
+
+        let elapsed = drip(&address, "\n# nothing\n", delay);
+        assert!(elapsed < IDLE_TIMEOUT * 3, "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn large_requests_are_rejected() {
+        let address = monitor("large");
+
+        // The monitor stops reading part way through, so the write may fail.
+        let mut connection = address.connect().unwrap();
+        let _ = connection.write_all(&[b'x'; MAX_REQUEST_SIZE as usize + 1]);
+
+        let mut line = String::new();
+        BufReader::new(connection).read_line(&mut line).unwrap();
+
+        let reply: Reply = serde_json::from_str(&line).unwrap();
+        assert_eq!(reply, Reply::error("request too large"));
+
+        assert_eq!(update(&address, "cpu"), Reply::ok(Value::from("updated")));
+    }
+}
@@ -0,0 +1,385 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Address, Command, Connection, Subscription};
+use crate::statusblock::Selector;
+
+/// The version of the protocol spoken by this version of abar. Requests with
+/// any other version are rejected.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A single command sent to a Monitor, as one line of JSON.
+///
+/// eg. `{"version":1,"command":"update","args":["cpu"]}`
+///
+/// Any number of Requests can be sent over one connection, a line at a time.
+/// Each one is sent its own Reply, in the same order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub version: u32,
+    pub command: String,
+    #[serde(default)]
+    pub args:    Vec<String>,
+}
+
+/// The answer to a Request, sent back as one line of JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reply {
+    pub version: u32,
+    pub status:  Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error:   Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data:    Value,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+impl Request {
+    pub fn new(command: &str, args: &[String]) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            command: String::from(command),
+            args:    args.to_vec(),
+        }
+    }
+
+    /// Reads a Request from a line of input. Lines that aren't JSON are read
+    /// as a command followed by its arguments, split up like a shell would
+    /// (see `split`).
+    ///
+    /// Returns None if there's nothing to run, ie. the line is blank or only
+    /// holds a comment.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+
+        if !line.starts_with('{') {
+            let words = split(line)?;
+            return Ok(words
+                .split_first()
+                .map(|(command, args)| Self::new(command, args)));
+        }
+
+        serde_json::from_str(line)
+            .map(Some)
+            .map_err(|err| format!("malformed request: {}", err))
+    }
+
+    /// Turns the Request into a Command that the bar can act on.
+    pub fn to_command(&self) -> Result<Command, String> {
+        if self.version != PROTOCOL_VERSION {
+            return Err(format!(
+                "unsupported protocol version {} (expected {})",
+                self.version, PROTOCOL_VERSION
+            ));
+        }
+
+        let usage = |usage: &str| Err(format!("usage: {}", usage));
+        let select = |selectors: &[String]| -> Vec<Selector> {
+            selectors.iter().map(|s| Selector::parse(s)).collect()
+        };
+
+        match (self.command.as_str(), self.args.as_slice()) {
+            ("refresh", _) => Ok(Command::Refresh),
+            ("shutdown", _) => Ok(Command::Shutdown),
+            ("update", selectors) => Ok(Command::Update(select(selectors))),
+            ("pause", []) => usage("pause BLOCK..."),
+            ("pause", selectors) => Ok(Command::Pause(select(selectors))),
+            ("resume", []) => usage("resume BLOCK..."),
+            ("resume", selectors) => Ok(Command::Resume(select(selectors))),
+            ("interval", [selector, interval]) => Ok(Command::Interval {
+                selector: Selector::parse(selector),
+                interval: match interval.as_str() {
+                    "off" => None,
+                    interval => Some(parse_duration(interval)?),
+                },
+            }),
+            ("interval", _) => usage("interval BLOCK SECONDS|off"),
+            ("get", []) => Ok(Command::Get(None)),
+            ("get", [selector]) =>
+                Ok(Command::Get(Some(Selector::parse(selector)))),
+            ("get", _) => usage("get [BLOCK]"),
+            ("list", []) => Ok(Command::List),
+            ("list", _) => usage("list"),
+            ("inspect", [selector]) =>
+                Ok(Command::Inspect(Selector::parse(selector))),
+            ("inspect", _) => usage("inspect BLOCK"),
+            ("set", [selector, text, ttl @ ..]) if ttl.len() <= 1 => {
+                let ttl = ttl.first().map(|ttl| parse_duration(ttl));
+                Ok(Command::Set {
+                    selector: Selector::parse(selector),
+                    text:     text.clone(),
+                    ttl:      ttl.transpose()?,
+                })
+            },
+            ("set", _) => usage("set BLOCK TEXT [TTL]"),
+            ("subscribe", []) => Ok(Command::Subscribe(Subscription::Text)),
+            ("subscribe", [kind]) => match kind.as_str() {
+                "text" => Ok(Command::Subscribe(Subscription::Text)),
+                "json" => Ok(Command::Subscribe(Subscription::Json)),
+                "changes" => Ok(Command::Subscribe(Subscription::Changes)),
+                _ => usage("subscribe [text|json|changes]"),
+            },
+            ("subscribe", _) => usage("subscribe [text|json|changes]"),
+            (command, _) => Err(format!("unknown command '{}'", command)),
+        }
+    }
+
+    /// Sends the Request to the Monitor at the given Address, and waits for
+    /// its Reply.
+    pub fn send(&self, address: &Address) -> io::Result<Reply> {
+        Client::connect(address)?.send(self)
+    }
+
+    /// Sends the Request to the Monitor at the given Address, and returns
+    /// each Reply as it arrives. Most Requests only get one Reply, but
+    /// subscriptions get one for each update.
+    pub fn stream(
+        &self, address: &Address,
+    ) -> io::Result<impl Iterator<Item = io::Result<Reply>>> {
+        let mut client = Client::connect(address)?;
+        client.write(self)?;
+        Ok(client.into_replies())
+    }
+}
+
+/// A connection to a Monitor, which can carry any number of Requests.
+pub struct Client {
+    connection: Connection,
+    reader:     BufReader<Connection>,
+}
+
+impl Client {
+    pub fn connect(address: &Address) -> io::Result<Self> {
+        let connection = address.connect()?;
+        let reader = BufReader::new(connection.try_clone()?);
+        Ok(Self { connection, reader })
+    }
+
+    /// Sends the Request, and waits for its Reply.
+    pub fn send(&mut self, request: &Request) -> io::Result<Reply> {
+        self.write(request)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    fn write(&mut self, request: &Request) -> io::Result<()> {
+        writeln!(self.connection, "{}", serde_json::to_string(request)?)
+    }
+
+    /// Returns each Reply as it arrives, until the Monitor hangs up.
+    fn into_replies(self) -> impl Iterator<Item = io::Result<Reply>> {
+        self.reader.lines().map(|line| Ok(serde_json::from_str(&line?)?))
+    }
+}
+
+/// Splits a line into words, like a (very) simple shell.
+///
+/// Words are separated by whitespace, unless it's inside of single or double
+/// quotes. Outside of quotes, a backslash keeps the next character as-is,
+/// and inside of double quotes it can escape `"` or `\`. Anything after an
+/// unquoted `#` at the start of a word is a comment.
+fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '#' if word.is_none() => break,
+            '\\' => {
+                let c = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(c);
+            },
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\')
+                            if c == '"'
+                                && matches!(chars.peek(), Some('"' | '\\')) =>
+                            word.extend(chars.next()),
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("unterminated quote")),
+                    }
+                }
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+    Ok(words)
+}
+
+/// Parses a duration such as "30", "30s", "5m" or "1h". Plain numbers are read
+/// as seconds.
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let (number, unit) = match duration.strip_suffix(['s', 'm', 'h']) {
+        Some(number) => (number, &duration[number.len()..]),
+        None => (duration, "s"),
+    };
+
+    let scale = match unit {
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => 1.0,
+    };
+
+    number
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * scale).ok())
+        .ok_or_else(|| format!("invalid duration '{}'", duration))
+}
+
+impl Reply {
+    pub fn ok(data: Value) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            status: Status::Ok,
+            error: None,
+            data,
+        }
+    }
+
+    pub fn error(message: &str) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            status: Status::Error,
+            error: Some(String::from(message)),
+            data: Value::Null,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool { self.status == Status::Ok }
+}
+
+impl From<Result<Value, String>> for Reply {
+    fn from(result: Result<Value, String>) -> Self {
+        match result {
+            Ok(data) => Reply::ok(data),
+            Err(message) => Reply::error(&message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn requests_can_be_json_or_plain_text() {
+        let json = r#"{"version":1,"command":"update","args":["cpu"]}"#;
+        let expected = Request::new("update", &[String::from("cpu")]);
+
+        assert_eq!(Request::parse(json), Ok(Some(expected.clone())));
+        assert_eq!(Request::parse("update  cpu\n"), Ok(Some(expected)));
+        assert!(Request::parse("{not json").is_err());
+        assert_eq!(Request::parse(""), Ok(None));
+        assert_eq!(Request::parse("  # just a comment"), Ok(None));
+    }
+
+    #[test]
+    fn plain_text_is_split_like_a_shell() {
+        let words = |line: &str| split(line).unwrap().join("|");
+
+        assert_eq!(words("set cpu 'a  b'"), "set|cpu|a  b");
+        assert_eq!(words(r#"set "my block" """#), "set|my block|");
+        assert_eq!(words(r#"a\ b "c\"d" 'e\f'"#), r#"a b|c"d|e\f"#);
+        assert_eq!(words("get cpu # the cpu"), "get|cpu");
+        assert_eq!(words("get c#pu"), "get|c#pu");
+
+        assert!(split("set cpu 'oops").is_err());
+        assert!(split(r"set cpu oops\").is_err());
+    }
+
+    #[test]
+    fn requests_are_checked_before_running() {
+        let mut request = Request::new("shutdown", &[]);
+        assert!(matches!(request.to_command(), Ok(Command::Shutdown)));
+
+        request.version = 2;
+        assert!(request.to_command().is_err());
+
+        assert!(Request::new("explode", &[]).to_command().is_err());
+        assert!(Request::new("inspect", &[]).to_command().is_err());
+    }
+
+    #[test]
+    fn set_accepts_an_optional_ttl() {
+        let args = |args: &[&str]| {
+            let args: Vec<String> =
+                args.iter().map(|arg| arg.to_string()).collect();
+            Request::new("set", &args).to_command()
+        };
+
+        match args(&["cpu", "hot", "5m"]) {
+            Ok(Command::Set { selector, text, ttl }) => {
+                assert_eq!(selector, Selector::parse("cpu"));
+                assert_eq!(text, "hot");
+                assert_eq!(ttl, Some(Duration::from_secs(300)));
+            },
+            _ => panic!("expected a set command"),
+        }
+
+        assert!(matches!(args(&["cpu", "hot"]), Ok(Command::Set { .. })));
+        assert!(args(&["cpu", "hot", "soon"]).is_err());
+        assert!(args(&["cpu", "hot", "-1"]).is_err());
+        assert!(args(&["cpu"]).is_err());
+    }
+
+    #[test]
+    fn timers_can_be_changed_or_turned_off() {
+        let interval = |args: &[&str]| {
+            let args: Vec<String> =
+                args.iter().map(|arg| arg.to_string()).collect();
+            Request::new("interval", &args).to_command()
+        };
+
+        match interval(&["tag:net", "1m"]) {
+            Ok(Command::Interval { selector, interval }) => {
+                assert_eq!(selector, Selector::Tag(String::from("net")));
+                assert_eq!(interval, Some(Duration::from_secs(60)));
+            },
+            _ => panic!("expected an interval command"),
+        }
+
+        assert!(matches!(
+            interval(&["cpu", "off"]),
+            Ok(Command::Interval { interval: None, .. })
+        ));
+        assert!(interval(&["cpu"]).is_err());
+        assert!(Request::new("pause", &[]).to_command().is_err());
+    }
+
+    #[test]
+    fn replies_skip_empty_fields() {
+        let reply = serde_json::to_value(Reply::ok(Value::Null)).unwrap();
+        assert_eq!(reply, json!({ "version": 1, "status": "ok" }));
+
+        let reply = serde_json::to_value(Reply::error("oops")).unwrap();
+        assert_eq!(reply, json!({
+            "version": 1,
+            "status": "error",
+            "error": "oops",
+        }));
+    }
+}
@@ -0,0 +1,90 @@
+use super::sanitise::{collapse_lines, sanitise};
+use super::{Format, Frame};
+use crate::style::Color;
+
+/// Draws the bar using ANSI escape sequences, for previewing it in a terminal.
+///
+/// Colors are drawn as 24-bit SGR sequences, and urgent blocks are drawn in
+/// reverse video. Control characters are removed from the text so that blocks
+/// can't mess with the terminal, although blocks with raw markup may draw
+/// their own escape sequences.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ansi;
+
+impl Format for Ansi {
+    fn format(&self, frame: &Frame) -> String {
+        let mut out = String::new();
+
+        for segment in &frame.segments {
+            let mut codes = Vec::new();
+            if let Some(color) = segment.style.foreground {
+                codes.push(sgr_color(38, color));
+            }
+            if let Some(color) = segment.style.background {
+                codes.push(sgr_color(48, color));
+            }
+            if segment.style.bold {
+                codes.push(String::from("1"));
+            }
+            if segment.style.underline {
+                codes.push(String::from("4"));
+            }
+            if segment.style.urgent {
+                codes.push(String::from("7"));
+            }
+
+            let text = if segment.raw_markup {
+                escape_raw(&segment.text)
+            }
+            else {
+                sanitise(&segment.text)
+            };
+
+            if codes.is_empty() {
+                out.push_str(&text);
+            }
+            else {
+                let codes = codes.join(";");
+                out.push_str(&format!("\x1b[{}m{}\x1b[0m", codes, text));
+            }
+        }
+
+        out
+    }
+}
+
+fn sgr_color(code: u8, color: Color) -> String {
+    format!("{};2;{};{};{}", code, color.r, color.g, color.b)
+}
+
+/// Removes any control characters from the text, except for escape.
+fn escape_raw(text: &str) -> String {
+    collapse_lines(text)
+        .chars()
+        .filter(|&c| c == '\x1b' || !c.is_control())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusbar::Segment;
+
+    #[test]
+    fn ansi_draws_colors_and_strips_escapes() {
+        let mut segment = Segment::text("hi");
+        segment.style.foreground = Some(Color::rgb(255, 0, 0));
+        segment.style.background = Some(Color::rgb(0, 0, 255));
+        segment.style.underline = true;
+
+        let frame = Frame {
+            segments: vec![segment, Segment::text("\x1b[2J!")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Ansi.format(&frame),
+            "\x1b[38;2;255;0;0;48;2;0;0;255;4mhi\x1b[0m[2J!"
+        );
+    }
+}
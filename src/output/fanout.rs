@@ -0,0 +1,182 @@
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{Frame, Output};
+
+/// Draws the bar to several Outputs at once.
+///
+/// Each Output runs on its own thread, so one that fails or is slow won't hold
+/// up the others (or the bar itself). If an Output falls behind, it will skip
+/// straight to the latest frame once it catches up.
+///
+/// When a Fanout is dropped, it waits (for up to a second) for each Output to
+/// finish drawing its current frame.
+#[derive(Default)]
+pub struct Fanout {
+    mailboxes: Vec<Arc<Mailbox>>,
+    threads:   Vec<JoinHandle<()>>,
+}
+
+/// How long dropping a Fanout will wait for its Outputs to finish.
+const DROP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Holds the latest frame that an Output hasn't drawn yet.
+#[derive(Default)]
+struct Mailbox {
+    frame:   Mutex<Slot>,
+    waiting: Condvar,
+}
+
+#[derive(Default)]
+struct Slot {
+    frame:  Option<Arc<Frame>>,
+    closed: bool,
+}
+
+impl Fanout {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds an Output, and starts a thread to draw to it.
+    pub fn output<O: Output + Send + 'static>(mut self, output: O) -> Self {
+        let mailbox = Arc::new(Mailbox::default());
+        self.mailboxes.push(mailbox.clone());
+
+        let thread = thread::spawn(move || Self::listen(output, mailbox));
+        self.threads.push(thread);
+        self
+    }
+
+    fn listen<O: Output>(mut output: O, mailbox: Arc<Mailbox>) {
+        loop {
+            let frame = {
+                let mut slot = mailbox.frame.lock().unwrap();
+                while slot.frame.is_none() && !slot.closed {
+                    slot = mailbox.waiting.wait(slot).unwrap();
+                }
+
+                match slot.frame.take() {
+                    Some(frame) => frame,
+                    None => return,
+                }
+            };
+
+            if let Err(err) = output.draw(&frame) {
+                eprintln!("Could not draw the bar: {}", err);
+            }
+        }
+    }
+}
+
+impl Output for Fanout {
+    /// Hands the frame to each Output without waiting for them to draw it.
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let frame = Arc::new(frame.clone());
+
+        for mailbox in &self.mailboxes {
+            mailbox.frame.lock().unwrap().frame = Some(frame.clone());
+            mailbox.waiting.notify_one();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Fanout {
+    // Let each thread finish drawing its current frame, then stop. Threads
+    // that are still stuck after DROP_TIMEOUT are left to finish on their own.
+    fn drop(&mut self) {
+        for mailbox in &self.mailboxes {
+            mailbox.frame.lock().unwrap().closed = true;
+            mailbox.waiting.notify_one();
+        }
+
+        let deadline = Instant::now() + DROP_TIMEOUT;
+        while Instant::now() < deadline {
+            let (finished, running) =
+                self.threads.drain(..).partition(|t| t.is_finished());
+            self.threads = running;
+
+            for thread in finished {
+                let _ = thread.join();
+            }
+
+            if self.threads.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test_utils::frame;
+
+    struct Channel(mpsc::Sender<String>);
+
+    impl Output for Channel {
+        fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+            self.0.send(frame.text()).unwrap();
+            Ok(())
+        }
+    }
+
+    struct Broken;
+
+    impl Output for Broken {
+        fn draw(&mut self, _frame: &Frame) -> io::Result<()> {
+            Err(io::Error::other("broken"))
+        }
+    }
+
+    struct Stuck;
+
+    impl Output for Stuck {
+        fn draw(&mut self, _frame: &Frame) -> io::Result<()> {
+            thread::sleep(Duration::from_secs(60));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dropping_waits_for_the_current_frame() {
+        struct Slow(mpsc::Sender<String>);
+
+        impl Output for Slow {
+            fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+                thread::sleep(Duration::from_millis(50));
+                self.0.send(frame.text()).unwrap();
+                Ok(())
+            }
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut fanout = Fanout::new().output(Slow(tx));
+
+        fanout.draw(&frame("last")).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        drop(fanout);
+
+        assert_eq!(rx.try_recv().unwrap(), "last");
+    }
+
+    #[test]
+    fn outputs_do_not_block_each_other() {
+        let (tx, rx) = mpsc::channel();
+        let mut fanout =
+            Fanout::new().output(Stuck).output(Broken).output(Channel(tx));
+
+        fanout.draw(&frame("first")).unwrap();
+        let timeout = Duration::from_secs(1);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), "first");
+
+        fanout.draw(&frame("second")).unwrap();
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), "second");
+    }
+}
@@ -0,0 +1,50 @@
+use super::sanitise::{sanitise, sanitise_frame};
+use super::Frame;
+
+/// Turns a Frame into a line of text for an Output to write.
+pub trait Format {
+    fn format(&self, frame: &Frame) -> String;
+}
+
+/// Draws the bar as plain text, without any styling. Line breaks and control
+/// characters are removed, so the bar will always fit on one line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Plain;
+
+impl Format for Plain {
+    fn format(&self, frame: &Frame) -> String {
+        frame.segments.iter().map(|segment| sanitise(&segment.text)).collect()
+    }
+}
+
+/// Draws the whole Frame as a single-line JSON object. Mostly useful for
+/// debugging, or for feeding the bar into other programs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn format(&self, frame: &Frame) -> String {
+        let frame = sanitise_frame(frame);
+        serde_json::to_string(&frame).expect("Frames can always be serialised")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusbar::Segment;
+
+    #[test]
+    fn json_includes_the_text() {
+        let frame = Frame {
+            segments: vec![Segment::text("hello")],
+            ..Default::default()
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&Json.format(&frame)).unwrap();
+
+        assert_eq!(json["segments"][0]["text"], "hello");
+        assert_eq!(json["segments"][0]["kind"], "text");
+    }
+}
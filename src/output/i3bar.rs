@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::{json, Value};
+
+use super::pango::span;
+use super::sanitise::{escape, escape_pango};
+use super::{Format, Frame};
+use crate::statusbar::Segment;
+use crate::style::Style;
+
+/// Draws the bar using i3bar's JSON protocol, for use as the status_command
+/// of i3 or sway.
+///
+/// The protocol's header is drawn along with the first frame, and each segment
+/// is drawn as a separate i3bar block. Colors and urgency are handled by i3bar
+/// itself, and bold or underlined text is drawn using Pango markup.
+#[derive(Debug, Default)]
+pub struct I3bar {
+    started: AtomicBool,
+}
+
+impl Format for I3bar {
+    fn format(&self, frame: &Frame) -> String {
+        let blocks: Vec<Value> = frame.segments.iter().map(block).collect();
+        let blocks = Value::Array(blocks);
+
+        if self.started.swap(true, Ordering::Relaxed) {
+            format!(",{}", blocks)
+        }
+        else {
+            format!("{}\n[\n{}", json!({ "version": 1 }), blocks)
+        }
+    }
+}
+
+fn block(segment: &Segment) -> Value {
+    let Style { foreground, background, bold, underline, urgent } =
+        segment.style;
+
+    let style = Style { bold, underline, ..Default::default() };
+    let text = span(&escape(segment, escape_pango), style);
+
+    let mut out = json!({
+        "full_text": text,
+        "markup": "pango",
+        "separator": false,
+        "separator_block_width": 0,
+    });
+
+    if let Some(name) = segment.name() {
+        out["name"] = json!(name);
+    }
+    if let Some(color) = foreground {
+        out["color"] = json!(color);
+    }
+    if let Some(color) = background {
+        out["background"] = json!(color);
+    }
+    if urgent {
+        out["urgent"] = json!(true);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::Color;
+    use crate::test_utils::block_segment;
+    use crate::State;
+
+    #[test]
+    fn i3bar_draws_the_header_once() {
+        let format = I3bar::default();
+        let frame = Frame::default();
+
+        assert_eq!(format.format(&frame), "{\"version\":1}\n[\n[]");
+        assert_eq!(format.format(&frame), ",[]");
+    }
+
+    #[test]
+    fn i3bar_draws_blocks() {
+        let segment = Segment {
+            style: Style::new().foreground(Color::rgb(255, 0, 0)).urgent(),
+            ..block_segment("cpu", "<5%>", State::Critical, Some(5.0))
+        };
+
+        assert_eq!(block(&segment), json!({
+            "full_text": "&lt;5%&gt;",
+            "markup": "pango",
+            "separator": false,
+            "separator_block_width": 0,
+            "name": "cpu",
+            "color": "#ff0000",
+            "urgent": true,
+        }));
+    }
+}
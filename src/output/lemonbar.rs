@@ -0,0 +1,292 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+
+use super::sanitise::escape;
+use super::{Format, Frame, Output};
+use crate::monitor::{self, MonitorSender};
+use crate::statusbar::{Alignment, Segment};
+use crate::statusblock::Selector;
+use crate::style::Style;
+
+/// Draws the bar using lemonbar's markup.
+///
+/// Each section is aligned with `%{l}`, `%{c}` and `%{r}`, and styles are drawn
+/// using `%{F}`, `%{B}` and `%{+u}`. Lemonbar can't draw bold text, so that's
+/// ignored, and urgent blocks are drawn with their colors swapped.
+///
+/// Named blocks are wrapped in click areas, which print the block's name when
+/// clicked. See LemonbarProcess for a way to respond to those clicks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lemonbar;
+
+impl Format for Lemonbar {
+    fn format(&self, frame: &Frame) -> String {
+        let mut out = String::new();
+
+        for (alignment, segments) in frame.sections.iter() {
+            if segments.is_empty() {
+                continue;
+            }
+
+            out.push_str(match alignment {
+                Alignment::Left => "%{l}",
+                Alignment::Center => "%{c}",
+                Alignment::Right => "%{r}",
+            });
+
+            for segment in segments {
+                draw_segment(&mut out, segment);
+            }
+        }
+
+        out
+    }
+}
+
+/// Stops text from being read as markup. Lemonbar only treats `%{` as special,
+/// so a zero-width space is slipped in between the two.
+fn escape_lemonbar(text: &str) -> String { text.replace("%{", "%\u{200b}{") }
+
+fn draw_segment(out: &mut String, segment: &Segment) {
+    let Style { foreground, background, underline, urgent, .. } =
+        segment.style;
+    let text = escape(segment, escape_lemonbar);
+
+    let name = segment.name();
+
+    if let Some(name) = name {
+        out.push_str(&format!("%{{A:{}:}}", name.replace(':', "\\:")));
+    }
+    if let Some(color) = foreground {
+        out.push_str(&format!("%{{F{}}}", color));
+    }
+    if let Some(color) = background {
+        out.push_str(&format!("%{{B{}}}", color));
+    }
+    if urgent {
+        out.push_str("%{R}");
+    }
+    if underline {
+        out.push_str("%{+u}");
+    }
+
+    out.push_str(&text);
+
+    if underline {
+        out.push_str("%{-u}");
+    }
+    if urgent {
+        out.push_str("%{R}");
+    }
+    if foreground.is_some() {
+        out.push_str("%{F-}");
+    }
+    if background.is_some() {
+        out.push_str("%{B-}");
+    }
+    if name.is_some() {
+        out.push_str("%{A}");
+    }
+}
+
+/// Runs lemonbar as a child process, and draws the bar to it.
+///
+/// Lemonbar prints the name of any block that's clicked, in which case the
+/// block will be updated immediately. If lemonbar exits, it will be restarted
+/// on the next frame.
+pub struct LemonbarProcess {
+    pub program: String,
+    pub args:    Vec<String>,
+    monitor_tx:  MonitorSender,
+    child:       Option<(Child, ChildStdin)>,
+}
+
+impl LemonbarProcess {
+    pub fn new(monitor_tx: MonitorSender) -> Self {
+        Self {
+            program: String::from("lemonbar"),
+            args: Vec::new(),
+            monitor_tx,
+            child: None,
+        }
+    }
+
+    /// Overrides the command used to run lemonbar.
+    pub fn program(mut self, program: &str) -> Self {
+        self.program = String::from(program);
+        self
+    }
+
+    /// Passes the given arguments to lemonbar, eg. ["-g", "x24", "-b"].
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args = args.iter().map(|arg| arg.to_string()).collect();
+        self
+    }
+
+    fn spawn(&self) -> io::Result<(Child, ChildStdin)> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let monitor_tx = self.monitor_tx.clone();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => handle_click(&line, &monitor_tx),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok((child, stdin))
+    }
+}
+
+/// Responds to a line printed by lemonbar, which should be the name of the
+/// block that was clicked.
+fn handle_click(line: &str, monitor_tx: &MonitorSender) {
+    let name = line.trim().replace("\\:", ":");
+
+    if !name.is_empty() {
+        let selector = Selector::Name(name);
+        let _ = monitor_tx.send(monitor::Command::Update(vec![selector]));
+    }
+}
+
+impl Output for LemonbarProcess {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.child.is_none() {
+            self.child = Some(self.spawn()?);
+        }
+
+        let (_, stdin) = self.child.as_mut().unwrap();
+        let result = writeln!(stdin, "{}", Lemonbar.format(frame));
+
+        // If lemonbar has gone away, clean up and restart it next time.
+        if result.is_err() {
+            if let Some((mut child, _)) = self.child.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+
+        result
+    }
+}
+
+impl Drop for LemonbarProcess {
+    fn drop(&mut self) {
+        if let Some((mut child, stdin)) = self.child.take() {
+            drop(stdin);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::statusbar::Sections;
+    use crate::style::Color;
+    use crate::test_utils::block_segment;
+    use crate::State;
+
+    fn block(name: &str, text: &str) -> Segment {
+        block_segment(name, text, State::Idle, None)
+    }
+
+    #[test]
+    fn lemonbar_draws_sections_and_click_areas() {
+        let frame = Frame {
+            sections: Sections {
+                left: vec![block("cpu", "5%")],
+                right: vec![Segment::text("|"), block("clock", "12:00")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Lemonbar.format(&frame),
+            "%{l}%{A:cpu:}5%%{A}%{r}|%{A:clock:}12:00%{A}"
+        );
+    }
+
+    #[test]
+    fn lemonbar_draws_colors() {
+        let mut segment = Segment::text("hi");
+        segment.style.foreground = Some(Color::rgb(255, 0, 0));
+        segment.style.background = Some(Color::rgb(0, 0, 0));
+        segment.style.urgent = true;
+
+        let frame = Frame {
+            sections: Sections { center: vec![segment], ..Default::default() },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Lemonbar.format(&frame),
+            "%{c}%{F#ff0000}%{B#000000}%{R}hi%{R}%{F-}%{B-}"
+        );
+    }
+
+    #[test]
+    fn lemonbar_escapes_block_text() {
+        let mut raw = block("raw", "%{F#ff0000}");
+        raw.raw_markup = true;
+
+        let frame = Frame {
+            sections: Sections {
+                left: vec![block("cpu", "%{r}\nx"), raw],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Lemonbar.format(&frame),
+            "%{l}%{A:cpu:}%\u{200b}{r} x%{A}%{A:raw:}%{F#ff0000}%{A}"
+        );
+    }
+
+    #[test]
+    fn clicks_update_blocks() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        handle_click("my\\:block\n", &tx);
+
+        match rx.try_recv() {
+            Ok(monitor::Command::Update(selectors)) =>
+                assert_eq!(selectors, [Selector::Name("my:block".into())]),
+            _ => panic!("expected an update"),
+        }
+    }
+
+    #[test]
+    fn lemonbar_processes_report_clicks() {
+        use std::time::Duration;
+
+        let (tx, rx) = mpsc::sync_channel(1);
+
+        // Stand in for lemonbar by reading one frame, then "clicking" on cpu.
+        let mut output = LemonbarProcess::new(tx)
+            .program("sh")
+            .args(&["-c", "read frame && echo cpu"]);
+
+        output.draw(&Frame::default()).unwrap();
+
+        match rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(monitor::Command::Update(selectors)) =>
+                assert_eq!(selectors, [Selector::parse("cpu")]),
+            _ => panic!("expected an update"),
+        }
+    }
+}
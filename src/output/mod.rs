@@ -0,0 +1,90 @@
+mod ansi;
+mod fanout;
+mod format;
+mod i3bar;
+mod lemonbar;
+mod pango;
+mod sanitise;
+mod sinks;
+mod tmux;
+mod waybar;
+#[cfg(feature = "x11")]
+mod x11;
+
+use std::io;
+
+pub use ansi::Ansi;
+pub use fanout::Fanout;
+pub use format::{Format, Json, Plain};
+pub use i3bar::I3bar;
+pub use lemonbar::{Lemonbar, LemonbarProcess};
+pub use pango::Pango;
+pub use sanitise::sanitise;
+use serde::Serialize;
+pub use sinks::{NamedPipe, Stdout, TextFile, XSetRoot};
+pub use tmux::Tmux;
+pub use waybar::Waybar;
+#[cfg(feature = "x11")]
+pub use x11::XRootName;
+
+use crate::statusbar::{Sections, Segment};
+
+/// The rendered state of a StatusBar. Outputs are given a new Frame every time
+/// that the bar changes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Frame {
+    /// The whole bar, with each section drawn one after the other.
+    pub segments: Vec<Segment>,
+    /// The bar split into separately aligned sections.
+    pub sections: Sections,
+}
+
+impl Frame {
+    /// Returns the bar as plain text, without any styling. The text isn't
+    /// sanitised, so it may contain newlines or control characters.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|segment| segment.text.as_str()).collect()
+    }
+}
+
+/// Somewhere that a StatusBar can be drawn to.
+///
+/// Outputs can hold on to whatever state they need between frames (open
+/// files, connections, etc.) and should return an error if a frame couldn't be
+/// drawn. Errors are reported, but won't stop the bar from running.
+///
+/// Most of the built-in Outputs take a Format, which decides how each Frame is
+/// turned into text. To draw to more than one Output at once, use a Fanout.
+pub trait Output {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()>;
+}
+
+/// Functions and closures can be used as Outputs, in which case they'll be
+/// given the bar as (sanitised) plain text.
+impl<F: FnMut(&str)> Output for F {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        self(&Plain.format(frame));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::frame;
+
+    fn draw<O: Output>(mut output: O, text: &str) {
+        output.draw(&frame(text)).unwrap();
+    }
+
+    fn ignore_bar(_text: &str) {}
+
+    #[test]
+    fn functions_and_closures_are_outputs() {
+        draw(ignore_bar, "hello");
+
+        let mut drawn = String::new();
+        draw(|text: &str| drawn.push_str(text), "hello");
+        assert_eq!(drawn, "hello");
+    }
+}
@@ -0,0 +1,69 @@
+use super::sanitise::{escape, escape_pango};
+use super::{Format, Frame};
+use crate::style::Style;
+
+/// Draws the bar as Pango markup, for anything that can display it.
+///
+/// Styles are drawn using `<span>` attributes. Pango has no notion of urgency,
+/// so urgent blocks are drawn as normal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pango;
+
+impl Format for Pango {
+    fn format(&self, frame: &Frame) -> String {
+        frame
+            .segments
+            .iter()
+            .map(|segment| span(&escape(segment, escape_pango), segment.style))
+            .collect()
+    }
+}
+
+/// Wraps the given (already escaped) text in a `<span>` that draws its Style,
+/// if it has one.
+pub(super) fn span(text: &str, style: Style) -> String {
+    let mut attributes = Vec::new();
+
+    if let Some(color) = style.foreground {
+        attributes.push(format!("foreground=\"{}\"", color));
+    }
+    if let Some(color) = style.background {
+        attributes.push(format!("background=\"{}\"", color));
+    }
+    if style.bold {
+        attributes.push(String::from("weight=\"bold\""));
+    }
+    if style.underline {
+        attributes.push(String::from("underline=\"single\""));
+    }
+
+    if attributes.is_empty() {
+        String::from(text)
+    }
+    else {
+        format!("<span {}>{}</span>", attributes.join(" "), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusbar::Segment;
+    use crate::style::Color;
+
+    #[test]
+    fn pango_draws_spans() {
+        let mut segment = Segment::text("a & b");
+        segment.style = Style::new().foreground(Color::rgb(0, 255, 0)).bold();
+
+        let frame = Frame {
+            segments: vec![segment, Segment::text(" <")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Pango.format(&frame),
+            "<span foreground=\"#00ff00\" weight=\"bold\">a &amp; b</span> &lt;"
+        );
+    }
+}
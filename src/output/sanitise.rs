@@ -0,0 +1,78 @@
+use super::Frame;
+use crate::statusbar::Segment;
+
+/// Makes text safe to draw on a single line, by collapsing any line breaks into
+/// spaces and removing any other control characters.
+///
+/// This doesn't escape any markup, as that depends on the Format.
+pub fn sanitise(text: &str) -> String {
+    collapse_lines(text)
+        .chars()
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+/// Collapses each run of line breaks into a single space. Line breaks at the
+/// start or end of the text are removed entirely.
+pub(super) fn collapse_lines(text: &str) -> String {
+    text.split(['\n', '\r'])
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Sanitises the segment's text, and escapes any markup in it (unless the
+/// segment allows raw markup).
+pub(super) fn escape(segment: &Segment, markup: fn(&str) -> String) -> String {
+    let text = sanitise(&segment.text);
+
+    if segment.raw_markup {
+        text
+    }
+    else {
+        markup(&text)
+    }
+}
+
+/// Escapes the characters that Pango would treat as markup.
+pub(super) fn escape_pango(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Returns a copy of the Frame with all of its text sanitised.
+pub(super) fn sanitise_frame(frame: &Frame) -> Frame {
+    let mut frame = frame.clone();
+
+    let sections = &mut frame.sections;
+    let segments = frame.segments.iter_mut().chain(sections.left.iter_mut());
+    let segments = segments
+        .chain(sections.center.iter_mut())
+        .chain(sections.right.iter_mut());
+
+    for segment in segments {
+        segment.text = sanitise(&segment.text);
+    }
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitise_collapses_lines_and_strips_control_chars() {
+        assert_eq!(sanitise("a\nb\r\n\nc\n"), "a b c");
+        assert_eq!(sanitise("\x1b[31mred\tx\x07"), "[31mred x");
+    }
+
+    #[test]
+    fn markup_is_only_escaped_when_not_raw() {
+        let mut segment = Segment::text("<b>1\n</b>");
+        assert_eq!(escape(&segment, escape_pango), "&lt;b&gt;1 &lt;/b&gt;");
+
+        segment.raw_markup = true;
+        assert_eq!(escape(&segment, escape_pango), "<b>1 </b>");
+    }
+}
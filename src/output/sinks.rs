@@ -0,0 +1,313 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::PathBuf;
+use std::process::Command;
+
+use super::{Format, Frame, Output, Plain};
+
+/// Prints each frame to stdout on its own line.
+pub struct Stdout {
+    pub format: Box<dyn Format + Send>,
+}
+
+impl Stdout {
+    pub fn format<F: Format + Send + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+}
+
+impl Default for Stdout {
+    fn default() -> Self { Self { format: Box::new(Plain) } }
+}
+
+impl Output for Stdout {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", self.format.format(frame))?;
+        stdout.flush()
+    }
+}
+
+/// Sets the name of the X root window by running `xsetroot -name`. This is
+/// where dwm (and a few other window managers) read their status from.
+pub struct XSetRoot {
+    pub format: Box<dyn Format + Send>,
+}
+
+impl XSetRoot {
+    pub fn format<F: Format + Send + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+}
+
+impl Default for XSetRoot {
+    fn default() -> Self { Self { format: Box::new(Plain) } }
+}
+
+impl Output for XSetRoot {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let status = Command::new("xsetroot")
+            .arg("-name")
+            .arg(self.format.format(frame))
+            .status()?;
+
+        if !status.success() {
+            let message = format!("xsetroot exited with {}", status);
+            return Err(io::Error::other(message));
+        }
+
+        Ok(())
+    }
+}
+
+/// Overwrites a file with each frame.
+///
+/// Frames are written to a temporary file before being moved into place, so
+/// anything reading the file will never see a partially-written bar. In append
+/// mode, each frame is added to the end of the file on its own line instead,
+/// eg. to keep a log.
+pub struct TextFile {
+    pub path:   PathBuf,
+    pub format: Box<dyn Format + Send>,
+    pub append: bool,
+}
+
+impl TextFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into(), format: Box::new(Plain), append: false }
+    }
+
+    pub fn format<F: Format + Send + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    /// Adds each frame to the end of the file, rather than replacing it.
+    pub fn append(mut self) -> Self {
+        self.append = true;
+        self
+    }
+}
+
+impl Output for TextFile {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.append {
+            let mut file =
+                OpenOptions::new().create(true).append(true).open(&self.path)?;
+            return writeln!(file, "{}", self.format.format(frame));
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, format!("{}\n", self.format.format(frame)))?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Writes each frame to a named pipe (FIFO) on its own line.
+///
+/// The pipe will be created if it doesn't already exist. Frames are skipped
+/// (rather than blocking the bar) while nothing is reading from the pipe, or
+/// if the reader has fallen behind.
+///
+/// Each line is sent with a single write, which the pipe only accepts whole,
+/// so the reader never sees part of a frame. Frames longer than PIPE_BUF
+/// (4096 bytes on Linux) can't be sent this way, and are rejected.
+pub struct NamedPipe {
+    pub path:   PathBuf,
+    pub format: Box<dyn Format + Send>,
+    pipe:       Option<fs::File>,
+}
+
+impl NamedPipe {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into(), format: Box::new(Plain), pipe: None }
+    }
+
+    pub fn format<F: Format + Send + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    /// Opens the pipe for writing, creating it first if needed. Returns None
+    /// if there's nothing on the other end to read from it.
+    fn open(&self) -> io::Result<Option<fs::File>> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) if !metadata.file_type().is_fifo() => {
+                let message = format!("{:?} is not a named pipe", self.path);
+                return Err(io::Error::other(message));
+            },
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound =>
+                mkfifo(&self.path)?,
+            Err(err) => return Err(err),
+        }
+
+        let pipe = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path);
+
+        match pipe {
+            Ok(pipe) => Ok(Some(pipe)),
+            // ENXIO means that the pipe doesn't have a reader yet.
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Output for NamedPipe {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.pipe.is_none() {
+            self.pipe = self.open()?;
+        }
+
+        let pipe = match &mut self.pipe {
+            Some(pipe) => pipe,
+            None => return Ok(()),
+        };
+
+        let line = format!("{}\n", self.format.format(frame));
+        if line.len() > libc::PIPE_BUF {
+            let message = format!(
+                "frame is too long for a named pipe ({} bytes, max {})",
+                line.len(),
+                libc::PIPE_BUF
+            );
+            return Err(io::Error::other(message));
+        }
+
+        match pipe.write(line.as_bytes()) {
+            Ok(n) if n == line.len() => Ok(()),
+            // Writes this small should be atomic, so this shouldn't happen.
+            // If it does, hang up rather than adding to a broken line.
+            Ok(_) => {
+                self.pipe = None;
+                Err(io::Error::other("only part of the frame was written"))
+            },
+            // The reader is busy, so skip this frame.
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            // The reader has gone away, so try to reopen the pipe next time.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                self.pipe = None;
+                Ok(())
+            },
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn mkfifo(path: &std::path::Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    // SAFETY: path is a valid, nul-terminated C string.
+    if unsafe { libc::mkfifo(path.as_ptr(), 0o600) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{frame, temp_path};
+
+    #[test]
+    fn text_files_are_overwritten() {
+        let path = temp_path("text-file");
+        let mut output = TextFile::new(&path);
+
+        output.draw(&frame("first")).unwrap();
+        output.draw(&frame("second")).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn text_files_can_be_appended_to() {
+        let path = temp_path("text-file-append");
+        let mut output = TextFile::new(&path).append();
+
+        output.draw(&frame("first")).unwrap();
+        output.draw(&frame("second")).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn named_pipes_skip_frames_without_a_reader() {
+        let path = temp_path("named-pipe");
+        let mut output = NamedPipe::new(&path);
+
+        output.draw(&frame("nobody is listening")).unwrap();
+        assert!(fs::metadata(&path).unwrap().file_type().is_fifo());
+
+        // Open the pipe for reading, and the next frame should arrive.
+        let reader = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .unwrap();
+
+        output.draw(&frame("hello")).unwrap();
+
+        let mut line = String::new();
+        io::BufRead::read_line(&mut io::BufReader::new(reader), &mut line)
+            .unwrap();
+        assert_eq!(line, "hello\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn named_pipes_only_send_whole_frames() {
+        let path = temp_path("named-pipe-full");
+        mkfifo(&path).unwrap();
+
+        let reader = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .unwrap();
+
+        // Keep drawing frames of different sizes until well past the pipe's
+        // capacity, so that some of them have to be skipped. Frames that are
+        // too long to send whole are rejected.
+        let frames: Vec<String> = (0..500)
+            .map(|i| format!("{}:{}", i, "x".repeat(i * 37 % 6000)))
+            .collect();
+
+        let mut output = NamedPipe::new(&path);
+        for text in &frames {
+            let result = output.draw(&frame(text));
+            assert_eq!(result.is_ok(), text.len() < libc::PIPE_BUF);
+        }
+
+        let mut drawn = frames.iter();
+        let mut lines = 0;
+        for line in io::BufRead::lines(io::BufReader::new(reader)) {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => panic!("{}", err),
+            };
+            assert!(drawn.any(|text| *text == line), "broken line: {}", line);
+            lines += 1;
+        }
+        assert!(lines > 0 && lines < frames.len());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use super::sanitise::escape;
+use super::{Format, Frame};
+use crate::statusbar::{Alignment, Segment};
+
+/// Draws the bar using tmux's style markup, eg. for use in `status-right`.
+///
+/// Styles are drawn with `#[fg=..,bg=..]` (urgent blocks are drawn in reverse),
+/// and any `#` in the text is escaped so that tmux won't try to interpret it.
+/// If the bar uses more than just the left section, each section is aligned
+/// with `#[align=..]`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tmux;
+
+impl Format for Tmux {
+    fn format(&self, frame: &Frame) -> String {
+        let sections = &frame.sections;
+        let aligned = !sections.center.is_empty() || !sections.right.is_empty();
+
+        if !aligned {
+            return draw_segments(&frame.segments);
+        }
+
+        let mut out = String::new();
+
+        for (alignment, segments) in sections.iter() {
+            if segments.is_empty() {
+                continue;
+            }
+
+            out.push_str(match alignment {
+                Alignment::Left => "#[align=left]",
+                Alignment::Center => "#[align=centre]",
+                Alignment::Right => "#[align=right]",
+            });
+            out.push_str(&draw_segments(segments));
+        }
+
+        out
+    }
+}
+
+fn draw_segments(segments: &[Segment]) -> String {
+    let mut out = String::new();
+
+    for segment in segments {
+        let text = escape(segment, |text| text.replace('#', "##"));
+
+        let mut styles = Vec::new();
+        if let Some(color) = segment.style.foreground {
+            styles.push(format!("fg={}", color));
+        }
+        if let Some(color) = segment.style.background {
+            styles.push(format!("bg={}", color));
+        }
+        if segment.style.bold {
+            styles.push(String::from("bold"));
+        }
+        if segment.style.underline {
+            styles.push(String::from("underscore"));
+        }
+        if segment.style.urgent {
+            styles.push(String::from("reverse"));
+        }
+
+        if styles.is_empty() {
+            out.push_str(&text);
+        }
+        else {
+            out.push_str(&format!("#[{}]{}#[default]", styles.join(","), text));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusbar::Sections;
+    use crate::style::{Color, Style};
+
+    #[test]
+    fn tmux_draws_styles_and_escapes_text() {
+        let mut segment = Segment::text("#1");
+        segment.style = Style::new().foreground(Color::rgb(255, 0, 0)).bold();
+
+        let frame = Frame {
+            segments: vec![segment, Segment::text(" #[x]")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Tmux.format(&frame),
+            "#[fg=#ff0000,bold]##1#[default] ##[x]"
+        );
+    }
+
+    #[test]
+    fn tmux_aligns_sections() {
+        let frame = Frame {
+            sections: Sections {
+                left: vec![Segment::text("a")],
+                right: vec![Segment::text("b")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(Tmux.format(&frame), "#[align=left]a#[align=right]b");
+    }
+}
@@ -0,0 +1,190 @@
+use serde_json::{json, Value};
+
+use super::pango::span;
+use super::sanitise::{escape, escape_pango};
+use super::{Format, Frame};
+use crate::statusbar::Segment;
+
+/// Draws the bar as JSON, for use as a Waybar custom module with
+/// `"return-type": "json"`.
+///
+/// Either a single block or the whole bar can be drawn. The class is taken
+/// from the block's State (or the most severe State in the bar). A single
+/// block's numeric value can also be sent as the percentage, if it is one.
+///
+/// Waybar treats the text as Pango markup, so it's escaped accordingly, and
+/// styles are drawn the same way as they are by the Pango format.
+#[derive(Clone, Debug, Default)]
+pub struct Waybar {
+    pub block:      Option<String>,
+    pub percentage: bool,
+}
+
+impl Waybar {
+    /// Draws the whole bar as a single module.
+    pub fn bar() -> Self { Self::default() }
+
+    /// Draws only the block with the given name.
+    ///
+    /// A BlockGroup is drawn as a single block, named after the group, so the
+    /// blocks inside of it can't be drawn on their own. Use the group's name
+    /// instead, or move the block out of the group.
+    pub fn block(name: &str) -> Self {
+        Self { block: Some(String::from(name)), ..Default::default() }
+    }
+
+    /// Sends the block's value as the percentage (clamped to 0-100), eg. for
+    /// Waybar's format-icons. Only use this if the value is a percentage.
+    pub fn percentage(mut self) -> Self {
+        self.percentage = true;
+        self
+    }
+}
+
+impl Format for Waybar {
+    fn format(&self, frame: &Frame) -> String {
+        let value = match &self.block {
+            Some(name) => format_block(frame, name, self.percentage),
+            None => format_bar(frame),
+        };
+
+        value.to_string()
+    }
+}
+
+fn format_block(frame: &Frame, name: &str, percentage: bool) -> Value {
+    let segment = frame.segments.iter().find(|s| s.name() == Some(name));
+
+    // Waybar hides modules with empty text, which is exactly what should
+    // happen if the block isn't being drawn.
+    let segment = match segment {
+        Some(segment) => segment,
+        None => return json!({ "text": "" }),
+    };
+
+    let text = escape(segment, escape_pango);
+
+    let mut out = json!({
+        "text": span(&text, segment.style),
+        "tooltip": format!("{}: {}", escape_pango(name), text),
+        "class": segment.state().to_string(),
+    });
+
+    if let Some(value) = segment.value().filter(|_| percentage) {
+        out["percentage"] = json!(value.clamp(0.0, 100.0).round() as u8);
+    }
+
+    out
+}
+
+fn format_bar(frame: &Frame) -> Value {
+    let blocks = frame.segments.iter().filter(|segment| segment.is_block());
+
+    let class = blocks.clone().map(Segment::state).max().unwrap_or_default();
+    let tooltip: Vec<String> = blocks
+        .filter_map(|segment| {
+            let name = escape_pango(segment.name()?);
+            Some(format!("{}: {}", name, escape(segment, escape_pango)))
+        })
+        .collect();
+
+    let text: String = frame
+        .segments
+        .iter()
+        .map(|segment| span(&escape(segment, escape_pango), segment.style))
+        .collect();
+
+    json!({
+        "text": text,
+        "tooltip": tooltip.join("\n"),
+        "class": class.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::block_segment;
+    use crate::{BlockGroupBuilder, State, StatusBarBuilder, StatusBlockBuilder};
+
+    fn frame() -> Frame {
+        let battery =
+            block_segment("battery", "bat: 12%", State::Critical, Some(12.0));
+        let clock = block_segment("clock", "12:00", State::Idle, Some(12.0));
+
+        let segments = vec![battery, Segment::text(" | "), clock];
+        Frame { segments, ..Default::default() }
+    }
+
+    fn parse(json: String) -> Value { serde_json::from_str(&json).unwrap() }
+
+    #[test]
+    fn waybar_draws_single_blocks() {
+        let json = parse(Waybar::block("battery").format(&frame()));
+
+        assert_eq!(json, json!({
+            "text": "bat: 12%",
+            "tooltip": "battery: bat: 12%",
+            "class": "critical",
+        }));
+    }
+
+    #[test]
+    fn waybar_only_sends_percentages_when_asked() {
+        let json = parse(Waybar::block("clock").format(&frame()));
+        assert_eq!(json.get("percentage"), None);
+
+        let waybar = Waybar::block("battery").percentage();
+        let json = parse(waybar.format(&frame()));
+        assert_eq!(json["percentage"], 12);
+    }
+
+    #[test]
+    fn waybar_hides_missing_blocks() {
+        let json = parse(Waybar::block("volume").format(&frame()));
+        assert_eq!(json, json!({ "text": "" }));
+    }
+
+    #[test]
+    fn waybar_draws_groups_as_one_block() {
+        let down = StatusBlockBuilder::default()
+            .name("down")
+            .function(|| String::from("↓12k"))
+            .build();
+        let net = BlockGroupBuilder::new(vec![down]).name("net").build();
+        net.update_now();
+
+        let bar = StatusBarBuilder::new(vec![net]).build();
+
+        let json = parse(Waybar::block("net").format(&bar.frame()));
+        assert_eq!(json["text"], "↓12k");
+
+        let json = parse(Waybar::block("down").format(&bar.frame()));
+        assert_eq!(json, json!({ "text": "" }));
+    }
+
+    #[test]
+    fn waybar_escapes_pango_markup() {
+        let mut frame = frame();
+        frame.segments[0].text = String::from("<b>\n");
+
+        let json = parse(Waybar::block("battery").format(&frame));
+        assert_eq!(json["text"], "&lt;b&gt;");
+
+        frame.segments[0].raw_markup = true;
+
+        let json = parse(Waybar::block("battery").format(&frame));
+        assert_eq!(json["text"], "<b>");
+    }
+
+    #[test]
+    fn waybar_draws_the_whole_bar() {
+        let json = parse(Waybar::bar().format(&frame()));
+
+        assert_eq!(json, json!({
+            "text": "bat: 12% | 12:00",
+            "tooltip": "battery: bat: 12%\nclock: 12:00",
+            "class": "critical",
+        }));
+    }
+}
@@ -0,0 +1,140 @@
+use std::io;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, PropMode, Window};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+use super::{Format, Frame, Output, Plain};
+
+/// Sets the name of the X root window directly, without spawning `xsetroot`.
+///
+/// A single connection to the X server is kept open between frames, and will
+/// be reopened if it's lost. Both WM_NAME and _NET_WM_NAME are set, so this
+/// works anywhere that XSetRoot does. Requires the "x11" feature.
+pub struct XRootName {
+    pub format: Box<dyn Format + Send>,
+    connection: Option<RootConnection>,
+}
+
+/// An open connection to the X server, along with everything needed to set
+/// the root window's name.
+struct RootConnection {
+    conn:        RustConnection,
+    root:        Window,
+    utf8_string: Atom,
+    net_wm_name: Atom,
+}
+
+impl XRootName {
+    pub fn format<F: Format + Send + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+}
+
+impl Default for XRootName {
+    fn default() -> Self { Self { format: Box::new(Plain), connection: None } }
+}
+
+impl RootConnection {
+    /// Connects to the display named by $DISPLAY.
+    fn open() -> io::Result<Self> {
+        let (conn, screen) = x11rb::connect(None).map_err(io::Error::other)?;
+        let root = conn.setup().roots[screen].root;
+
+        let utf8_string = intern(&conn, b"UTF8_STRING")?;
+        let net_wm_name = intern(&conn, b"_NET_WM_NAME")?;
+
+        Ok(Self { conn, root, utf8_string, net_wm_name })
+    }
+
+    fn set_name(&self, name: &str) -> io::Result<()> {
+        let name = name.as_bytes();
+        let replace = PropMode::REPLACE;
+
+        // WM_NAME is set as a STRING (like xsetroot does) for the sake of
+        // older window managers. Most of them will happily read UTF-8 anyway.
+        self.conn
+            .change_property8(
+                replace,
+                self.root,
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                name,
+            )
+            .map_err(io::Error::other)?;
+
+        self.conn
+            .change_property8(
+                replace,
+                self.root,
+                self.net_wm_name,
+                self.utf8_string,
+                name,
+            )
+            .map_err(io::Error::other)?;
+
+        self.conn.flush().map_err(io::Error::other)
+    }
+}
+
+fn intern(conn: &RustConnection, name: &[u8]) -> io::Result<Atom> {
+    let cookie = conn.intern_atom(false, name).map_err(io::Error::other)?;
+    let reply = cookie.reply().map_err(io::Error::other)?;
+    Ok(reply.atom)
+}
+
+impl Output for XRootName {
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.connection.is_none() {
+            self.connection = Some(RootConnection::open()?);
+        }
+
+        let connection = self.connection.as_ref().unwrap();
+        let result = connection.set_name(&self.format.format(frame));
+
+        // If anything went wrong, start from scratch on the next frame.
+        if result.is_err() {
+            self.connection = None;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusbar::Segment;
+
+    /// Needs a running X server, eg. via xvfb-run (see the CI workflow).
+    #[test]
+    #[ignore]
+    fn root_window_name_is_set() {
+        let mut output = XRootName::default();
+        let frame = Frame {
+            segments: vec![Segment::text("hello from abar ✓")],
+            ..Default::default()
+        };
+
+        output.draw(&frame).unwrap();
+
+        let connection = RootConnection::open().unwrap();
+        let property = connection
+            .conn
+            .get_property(
+                false,
+                connection.root,
+                connection.net_wm_name,
+                connection.utf8_string,
+                0,
+                u32::MAX,
+            )
+            .unwrap()
+            .reply()
+            .unwrap();
+
+        assert_eq!(property.value, "hello from abar ✓".as_bytes());
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+
+pub use libc::{SIGUSR1, SIGUSR2};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator;
+
+use crate::monitor::{Command, MonitorSender};
+use crate::StatusBar;
+
+/// Returns the real-time signal `SIGRTMIN+n`, eg. for binding a block to
+/// `pkill -RTMIN+10 abar`.
+///
+/// # Panics
+///
+/// Will panic if the signal would be past SIGRTMAX.
+pub fn rtmin(n: i32) -> i32 {
+    let signal = libc::SIGRTMIN() + n;
+    assert!(
+        (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&signal),
+        "SIGRTMIN+{} is not a real-time signal",
+        n
+    );
+    signal
+}
+
+/// A struct for reacting to Unix signals, much like the Monitor reacts to
+/// commands.
+///
+/// Blocks that were bound to a signal (see StatusBlockBuilder::signal) are
+/// updated whenever it's received. SIGTERM and SIGINT shut the bar down
+/// cleanly, the same as the `shutdown` command. If either of them is received
+/// again before the bar has stopped, the process exits immediately.
+pub struct Signals {
+    signals: iterator::Signals,
+    tx:      MonitorSender,
+}
+
+impl Signals {
+    /// Starts listening for the signals used by the given bar, along with
+    /// SIGTERM and SIGINT.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any block is bound to a signal that can't be caught, such
+    /// as SIGKILL.
+    pub fn new(tx: MonitorSender, bar: &StatusBar) -> Self {
+        let signals = bar.walk().filter_map(|block| block.signal);
+        Self::with_signals(tx, signals.chain([SIGTERM, SIGINT]))
+    }
+
+    /// Starts listening for the given signals only. SIGTERM and SIGINT will
+    /// only shut the bar down if they're included.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any of the signals can't be caught, such as SIGKILL.
+    pub fn with_signals(
+        tx: MonitorSender, wanted: impl IntoIterator<Item = i32>,
+    ) -> Self {
+        let wanted: BTreeSet<i32> = wanted.into_iter().collect();
+        let signals = iterator::Signals::new(&wanted).unwrap_or_else(|err| {
+            panic!("Could not listen for signals {:?}: {}", wanted, err)
+        });
+
+        Self { signals, tx }
+    }
+
+    /// Wait for signals, and pipe the corresponding commands into self.tx.
+    ///
+    /// Returns once the bar has stopped listening, unless it was asked to shut
+    /// down, in which case the process exits with an error.
+    pub fn run(mut self) {
+        let mut shutting_down = false;
+
+        for signal in self.signals.forever() {
+            let command = match signal {
+                SIGTERM | SIGINT if shutting_down => std::process::exit(1),
+                SIGTERM | SIGINT => {
+                    shutting_down = true;
+                    Command::Shutdown
+                },
+                signal => Command::Signal(signal),
+            };
+
+            // The bar has already gone, so it can't shut down cleanly.
+            if self.tx.send(command).is_err() {
+                if shutting_down {
+                    std::process::exit(1);
+                }
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{StatusBarBuilder, StatusBlockBuilder};
+
+    #[test]
+    fn real_time_signals_are_offset_from_rtmin() {
+        assert_eq!(rtmin(0), libc::SIGRTMIN());
+        assert_eq!(rtmin(10), libc::SIGRTMIN() + 10);
+    }
+
+    #[test]
+    fn block_signals_are_forwarded() {
+        let block = StatusBlockBuilder::default()
+            .name("cpu")
+            .signal(rtmin(3))
+            .build();
+        let bar = StatusBarBuilder::new(vec![block]).build();
+
+        let wanted: Vec<i32> =
+            bar.walk().filter_map(|block| block.signal).collect();
+        assert_eq!(wanted, [rtmin(3)]);
+
+        // Only listen for the block's signal, so that the rest of the tests
+        // can still be interrupted.
+        let (tx, rx) = mpsc::sync_channel(1);
+        let signals = Signals::with_signals(tx, wanted);
+        thread::spawn(move || signals.run());
+
+        signal_hook::low_level::raise(rtmin(3)).unwrap();
+
+        match rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Command::Signal(signal)) => assert_eq!(signal, rtmin(3)),
+            _ => panic!("expected a signal"),
+        }
+    }
+}
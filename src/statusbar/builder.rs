@@ -1,3 +1,4 @@
+use super::{Layout, LayoutError};
 use crate::{StatusBar, StatusBlock};
 
 #[derive(Default)]
@@ -7,6 +8,9 @@ pub struct StatusBarBuilder {
     pub left_buffer:        String,
     pub right_buffer:       String,
     pub hide_empty_modules: bool,
+    pub layout:             Option<Layout>,
+    pub powerline:          bool,
+    pub max_width:          Option<usize>,
 }
 
 #[allow(dead_code)]
@@ -15,7 +19,19 @@ impl StatusBarBuilder {
         Self { blocks, ..Default::default() }
     }
 
-    pub fn build(self) -> StatusBar { self.into() }
+    /// # Panics
+    ///
+    /// Will panic if the Layout refers to a block that the bar doesn't have.
+    /// See try_build.
+    pub fn build(self) -> StatusBar {
+        self.try_build().unwrap_or_else(|err| panic!("Invalid layout: {}", err))
+    }
+
+    /// Builds the bar, or returns an error if the Layout refers to a block
+    /// that the bar doesn't have.
+    pub fn try_build(self) -> Result<StatusBar, LayoutError> {
+        self.try_into()
+    }
 
     pub fn blocks(mut self, blocks: Vec<StatusBlock>) -> Self {
         self.blocks = blocks;
@@ -41,16 +57,67 @@ impl StatusBarBuilder {
         self.hide_empty_modules = hide_empty_modules;
         self
     }
+
+    /// Arranges the bar's blocks using a template. See Layout for the syntax.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the template can't be parsed. See try_layout.
+    pub fn layout(self, template: &str) -> Self {
+        self.try_layout(template).unwrap_or_else(|err| {
+            panic!("Invalid layout {:?}: {}", template, err)
+        })
+    }
+
+    /// Arranges the bar's blocks using a template, or returns an error if it
+    /// can't be parsed.
+    pub fn try_layout(mut self, template: &str) -> Result<Self, LayoutError> {
+        self.layout = Some(Layout::parse(template)?);
+        Ok(self)
+    }
+
+    /// Draws delimiters as powerline-style transitions between the background
+    /// colors of the blocks on either side. Set the delimiter (and buffers) to
+    /// the glyphs that you'd like to use, eg. "\u{e0b0}".
+    pub fn powerline(mut self, powerline: bool) -> Self {
+        self.powerline = powerline;
+        self
+    }
+
+    /// Limits the width of the bar (in characters). Whenever the bar is too
+    /// wide, the blocks with the lowest priority are switched to their short
+    /// text until it fits, and if that isn't enough, they're hidden instead.
+    /// Blocks further to the right are collapsed first if priorities are equal,
+    /// and nothing is collapsed unless it needs to be.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
 }
 
-impl From<StatusBarBuilder> for StatusBar {
-    fn from(builder: StatusBarBuilder) -> Self {
-        Self {
+impl TryFrom<StatusBarBuilder> for StatusBar {
+    type Error = LayoutError;
+
+    fn try_from(builder: StatusBarBuilder) -> Result<Self, Self::Error> {
+        let bar = Self {
             blocks:             builder.blocks,
             delimiter:          builder.delimiter,
             left_buffer:        builder.left_buffer,
             right_buffer:       builder.right_buffer,
             hide_empty_modules: builder.hide_empty_modules,
+            layout:             builder.layout,
+            powerline:          builder.powerline,
+            max_width:          builder.max_width,
+        };
+
+        if let Some(layout) = &bar.layout {
+            for name in layout.block_names() {
+                if bar.find(name).is_none() {
+                    return Err(LayoutError::UnknownBlock(String::from(name)));
+                }
+            }
         }
+
+        Ok(bar)
     }
 }
@@ -0,0 +1,295 @@
+use std::slice;
+
+use serde_json::{json, Value};
+
+use super::StatusBar;
+use crate::monitor::Command;
+use crate::output::{sanitise, Format, Plain};
+use crate::statusblock::{Selector, StatusBlock};
+
+impl StatusBar {
+    /// Carries out a Command that was sent to the bar, returning any data that
+    /// should be sent back to whoever sent it.
+    pub(super) fn execute(&self, command: &Command) -> Result<Value, String> {
+        match command {
+            Command::Update(selectors) =>
+                self.each(selectors, StatusBlock::update_now),
+            Command::Signal(signal) => {
+                self.update_signalled(*signal);
+                Ok(Value::Null)
+            },
+            Command::Pause(selectors) =>
+                self.each(selectors, StatusBlock::pause),
+            Command::Resume(selectors) =>
+                self.each(selectors, StatusBlock::resume),
+            // A block that updates constantly would keep the bar busy.
+            Command::Interval { interval: Some(interval), .. }
+                if interval.is_zero() =>
+                Err(String::from("the interval must be greater than zero")),
+            Command::Interval { selector, interval } =>
+                self.each(slice::from_ref(selector), |block| {
+                    block.set_update_interval(*interval)
+                }),
+            Command::Get(None) => Ok(json!(Plain.format(&self.frame()))),
+            Command::Get(Some(selector)) =>
+                self.describe(selector, |block| {
+                    json!(sanitise(&block.to_string()))
+                }),
+            Command::List => {
+                let names: Vec<&str> =
+                    self.walk().filter_map(|b| b.name.as_deref()).collect();
+                Ok(json!(names))
+            },
+            Command::Inspect(selector) =>
+                self.describe(selector, |block| json!(block.info())),
+            Command::Set { selector, text, ttl } => {
+                let selectors = slice::from_ref(selector);
+
+                let blocks = self.select(selectors);
+                if let Some(group) = blocks.iter().find(|b| b.group.is_some()) {
+                    let name = group.name.clone().unwrap_or_default();
+                    return Err(format!("can't set {}, as it's a group", name));
+                }
+
+                self.each(selectors, |block| block.set(text, *ttl))
+            },
+            Command::Refresh | Command::Shutdown => Ok(Value::Null),
+            Command::Subscribe(_) =>
+                Err(String::from("subscriptions need somewhere to send to")),
+            Command::Request { .. } =>
+                Err(String::from("requests can't be nested")),
+        }
+    }
+
+    /// Describes the blocks that match the Selector. Names give a single
+    /// description, while globs and tags give a list of them (along with each
+    /// block's name).
+    fn describe(
+        &self, selector: &Selector, f: impl Fn(&StatusBlock) -> Value,
+    ) -> Result<Value, String> {
+        let blocks = self.select(slice::from_ref(selector));
+        if blocks.is_empty() {
+            return Err(format!("no such block: {}", selector));
+        }
+
+        match selector {
+            Selector::Name(_) => Ok(f(blocks[0])),
+            _ => Ok(blocks
+                .into_iter()
+                .map(|block| json!({ "name": block.name, "value": f(block) }))
+                .collect()),
+        }
+    }
+
+    /// Calls the function on every block that matches one of the Selectors.
+    /// Any Selectors that don't match anything are reported once the rest
+    /// have been handled.
+    fn each(
+        &self, selectors: &[Selector], f: impl Fn(&StatusBlock),
+    ) -> Result<Value, String> {
+        self.select(selectors).into_iter().for_each(f);
+
+        let missing: Vec<String> = selectors
+            .iter()
+            .filter(|s| self.select(slice::from_ref(s)).is_empty())
+            .map(Selector::to_string)
+            .collect();
+
+        match missing.as_slice() {
+            [] => Ok(Value::Null),
+            missing => Err(format!("no such block: {}", missing.join(", "))),
+        }
+    }
+
+    /// Updates any blocks that are bound to the signal. Groups that are bound
+    /// update all of their children.
+    fn update_signalled(&self, signal: i32) {
+        let mut blocks = self.walk();
+
+        while let Some(block) = blocks.next() {
+            if block.signal == Some(signal) {
+                block.update_now();
+                blocks.skip_children();
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{BlockGroupBuilder, StatusBarBuilder, StatusBlockBuilder};
+
+    fn bar() -> StatusBar {
+        let cpu = StatusBlockBuilder::new(|| String::from("5%\n"))
+            .name("cpu")
+            .build();
+        let wlan = StatusBlockBuilder::new(|| String::from("up"))
+            .name("wlan")
+            .tag("net")
+            .build();
+        let net = BlockGroupBuilder::new(vec![wlan]).name("net").build();
+
+        StatusBarBuilder::new(vec![cpu, net]).delimiter(" | ").build()
+    }
+
+    #[test]
+    fn get_returns_the_bar_or_a_block() {
+        let bar = bar();
+        let get = |selector: &str| {
+            bar.execute(&Command::Get(Some(Selector::parse(selector))))
+        };
+
+        assert_eq!(bar.execute(&Command::Get(None)), Ok(json!("5% | up")));
+        assert_eq!(get("cpu"), Ok(json!("5%")));
+        assert!(get("gpu").is_err());
+    }
+
+    #[test]
+    fn get_lists_blocks_matched_by_globs_and_tags() {
+        let bar = bar();
+        let get = |selector: &str| {
+            bar.execute(&Command::Get(Some(Selector::parse(selector))))
+        };
+
+        let wlan = json!([{ "name": "wlan", "value": "up" }]);
+        assert_eq!(get("tag:net"), Ok(wlan.clone()));
+        assert_eq!(get("wl*"), Ok(wlan));
+        assert_eq!(get("gpu*"), Err(String::from("no such block: gpu*")));
+    }
+
+    #[test]
+    fn list_includes_nested_blocks() {
+        let names = bar().execute(&Command::List);
+        assert_eq!(names, Ok(json!(["cpu", "net", "wlan"])));
+    }
+
+    #[test]
+    fn inspect_describes_the_block() {
+        let info = bar().execute(&Command::Inspect(Selector::parse("cpu")));
+        let info = info.unwrap();
+
+        assert_eq!(info["name"], "cpu");
+        assert_eq!(info["value"], 5.0);
+        assert_eq!(info["paused"], false);
+    }
+
+    #[test]
+    fn set_overwrites_blocks() {
+        let bar = bar();
+        let set = |selector: &str| Command::Set {
+            selector: Selector::parse(selector),
+            text:     String::from("hot"),
+            ttl:      None,
+        };
+
+        assert_eq!(bar.execute(&set("cpu")), Ok(Value::Null));
+        assert_eq!(bar.to_string(), "hot | up");
+        assert!(bar.execute(&set("net")).is_err());
+    }
+
+    #[test]
+    fn updating_missing_blocks_is_an_error() {
+        let cpu = StatusBlockBuilder::default().name("cpu").build();
+        let bar = StatusBarBuilder::new(vec![cpu]).build();
+
+        let update = |selectors: &[&str]| {
+            let selectors = selectors.iter().map(|s| Selector::parse(s));
+            bar.execute(&Command::Update(selectors.collect()))
+        };
+
+        assert_eq!(update(&["cpu"]), Ok(Value::Null));
+        assert_eq!(
+            update(&["cpu", "gpu", "fan"]),
+            Err(String::from("no such block: gpu, fan"))
+        );
+    }
+
+    #[test]
+    fn commands_act_on_every_selected_block() {
+        let block = |name: &str, tag: &str| {
+            StatusBlockBuilder::default().name(name).tag(tag).build()
+        };
+        let bar = StatusBarBuilder::new(vec![
+            block("cpu0", "cpu"),
+            block("cpu1", "cpu"),
+            block("wlan", "net"),
+        ])
+        .delimiter(" ")
+        .build();
+
+        let set = |selector: &str| Command::Set {
+            selector: Selector::parse(selector),
+            text:     String::from("x"),
+            ttl:      None,
+        };
+
+        assert_eq!(bar.execute(&set("cpu*")), Ok(Value::Null));
+        assert_eq!(bar.to_string(), "x x ");
+        assert_eq!(bar.execute(&set("tag:net")), Ok(Value::Null));
+        assert_eq!(bar.to_string(), "x x x");
+
+        let selectors = vec![Selector::parse("tag:cpu")];
+        assert_eq!(bar.execute(&Command::Pause(selectors)), Ok(Value::Null));
+        assert!(bar.find("cpu0").unwrap().info().paused);
+        assert!(!bar.find("wlan").unwrap().info().paused);
+
+        let selectors = vec![Selector::parse("cpu?")];
+        assert_eq!(bar.execute(&Command::Resume(selectors)), Ok(Value::Null));
+        assert!(!bar.find("cpu1").unwrap().info().paused);
+
+        let interval = Command::Interval {
+            selector: Selector::parse("wlan"),
+            interval: Some(Duration::from_secs(3)),
+        };
+        assert_eq!(bar.execute(&interval), Ok(Value::Null));
+        assert_eq!(bar.find("wlan").unwrap().info().interval, Some(3.0));
+
+        let interval = Command::Interval {
+            selector: Selector::parse("wlan"),
+            interval: Some(Duration::ZERO),
+        };
+        assert!(bar.execute(&interval).is_err());
+        assert_eq!(bar.find("wlan").unwrap().info().interval, Some(3.0));
+
+        let selectors = vec![Selector::parse("gpu*")];
+        assert_eq!(
+            bar.execute(&Command::Pause(selectors)),
+            Err(String::from("no such block: gpu*"))
+        );
+    }
+
+    #[test]
+    fn signals_update_bound_blocks() {
+        let block = |signal: i32| {
+            StatusBlockBuilder::new(|| String::from("fresh"))
+                .signal(signal)
+                .build()
+        };
+        let group = BlockGroupBuilder::new(vec![block(2)]).build();
+        let bar = StatusBarBuilder::new(vec![block(1), group])
+            .delimiter(" ")
+            .build();
+
+        bar.blocks[0].set("stale", None);
+        bar.blocks[1].group.as_ref().unwrap().blocks[0].set("stale", None);
+
+        assert_eq!(bar.execute(&Command::Signal(2)), Ok(Value::Null));
+        assert_eq!(bar.to_string(), "stale fresh");
+    }
+
+    #[test]
+    fn signals_update_bound_groups() {
+        let block = StatusBlockBuilder::new(|| String::from("fresh")).build();
+        let group = BlockGroupBuilder::new(vec![block]).signal(3).build();
+        let bar = StatusBarBuilder::new(vec![group]).build();
+
+        bar.blocks[0].group.as_ref().unwrap().blocks[0].set("stale", None);
+
+        assert_eq!(bar.execute(&Command::Signal(3)), Ok(Value::Null));
+        assert_eq!(bar.to_string(), "fresh");
+    }
+}
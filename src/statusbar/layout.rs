@@ -0,0 +1,238 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::Alignment;
+
+/// A template describing how the blocks in a StatusBar should be laid out.
+///
+/// Blocks are referred to by name using `{name}` (which must belong to one of
+/// the bar's blocks), and any other text is drawn as-is. Anything wrapped in
+/// square brackets is a conditional section, which will disappear if any of
+/// the blocks directly inside of it are hidden.
+/// Backslashes can be used to escape any of `{}[]\`.
+///
+/// Everything is drawn in the left section of the bar by default. `{:left}`,
+/// `{:center}` and `{:right}` can be used to move on to a different section,
+/// although not from inside of a conditional section.
+///
+/// eg. `"{cpu} {mem} │ [{vol} │ ]{clock}"`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub tokens: Vec<Token>,
+}
+
+/// A single piece of a parsed Layout.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Text(String),
+    Block(String),
+    Section(Vec<Token>),
+    Align(Alignment),
+}
+
+/// Describes why a template could not be parsed into a Layout, or why a
+/// Layout doesn't fit the bar that it was given to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayoutError {
+    UnclosedBlock,
+    UnclosedSection,
+    UnexpectedChar(char),
+    UnknownAlignment(String),
+    NestedAlignment,
+    UnknownBlock(String),
+}
+
+impl Layout {
+    pub fn parse(template: &str) -> Result<Self, LayoutError> {
+        let mut chars = template.chars();
+        let tokens = parse_tokens(&mut chars, false)?;
+        Ok(Self { tokens })
+    }
+
+    /// Returns the name of every block that the Layout refers to, in order.
+    pub fn block_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect_names(&self.tokens, &mut names);
+        names
+    }
+}
+
+fn collect_names<'a>(tokens: &'a [Token], names: &mut Vec<&'a str>) {
+    for token in tokens {
+        match token {
+            Token::Block(name) => names.push(name),
+            Token::Section(tokens) => collect_names(tokens, names),
+            Token::Text(_) | Token::Align(_) => (),
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = LayoutError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Self::parse(template)
+    }
+}
+
+/// Parses tokens until the end of the input, or until the end of the current
+/// section if `in_section` is set.
+fn parse_tokens(
+    chars: &mut std::str::Chars, in_section: bool,
+) -> Result<Vec<Token>, LayoutError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        let c = match chars.next() {
+            Some(c) => c,
+            None if in_section => return Err(LayoutError::UnclosedSection),
+            None => break,
+        };
+
+        match c {
+            '\\' => text.extend(chars.next()),
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(LayoutError::UnclosedBlock),
+                    }
+                }
+
+                flush_text(&mut tokens, &mut text);
+
+                match name.strip_prefix(':') {
+                    Some(_) if in_section =>
+                        return Err(LayoutError::NestedAlignment),
+                    Some(alignment) =>
+                        tokens.push(Token::Align(parse_alignment(alignment)?)),
+                    None => tokens.push(Token::Block(name)),
+                }
+            },
+            '[' => {
+                flush_text(&mut tokens, &mut text);
+                tokens.push(Token::Section(parse_tokens(chars, true)?));
+            },
+            ']' if in_section => break,
+            ']' | '}' => return Err(LayoutError::UnexpectedChar(c)),
+            c => text.push(c),
+        }
+    }
+
+    flush_text(&mut tokens, &mut text);
+    Ok(tokens)
+}
+
+fn parse_alignment(name: &str) -> Result<Alignment, LayoutError> {
+    match name {
+        "left" => Ok(Alignment::Left),
+        "center" => Ok(Alignment::Center),
+        "right" => Ok(Alignment::Right),
+        _ => Err(LayoutError::UnknownAlignment(String::from(name))),
+    }
+}
+
+fn flush_text(tokens: &mut Vec<Token>, text: &mut String) {
+    if !text.is_empty() {
+        tokens.push(Token::Text(std::mem::take(text)));
+    }
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::UnclosedBlock => write!(f, "expected '}}'"),
+            LayoutError::UnclosedSection => write!(f, "expected ']'"),
+            LayoutError::UnexpectedChar(c) =>
+                write!(f, "unexpected '{}' (use '\\{}' to escape it)", c, c),
+            LayoutError::UnknownAlignment(name) =>
+                write!(f, "unknown alignment '{}'", name),
+            LayoutError::NestedAlignment =>
+                write!(f, "alignments can't be changed inside of a section"),
+            LayoutError::UnknownBlock(name) =>
+                write!(f, "there's no block named '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_parse_blocks_and_text() {
+        let layout = Layout::parse("{cpu} │ {clock}").unwrap();
+
+        assert_eq!(layout.tokens, vec![
+            Token::Block(String::from("cpu")),
+            Token::Text(String::from(" │ ")),
+            Token::Block(String::from("clock")),
+        ]);
+    }
+
+    #[test]
+    fn layouts_parse_nested_sections() {
+        let layout = Layout::parse("[{vol}[ {mic}] │ ]").unwrap();
+
+        assert_eq!(layout.tokens, vec![Token::Section(vec![
+            Token::Block(String::from("vol")),
+            Token::Section(vec![
+                Token::Text(String::from(" ")),
+                Token::Block(String::from("mic")),
+            ]),
+            Token::Text(String::from(" │ ")),
+        ])]);
+    }
+
+    #[test]
+    fn layouts_respect_escapes() {
+        let layout = Layout::parse("\\[{net}\\]").unwrap();
+
+        assert_eq!(layout.tokens, vec![
+            Token::Text(String::from("[")),
+            Token::Block(String::from("net")),
+            Token::Text(String::from("]")),
+        ]);
+    }
+
+    #[test]
+    fn layouts_parse_alignments() {
+        let layout = Layout::parse("{a}{:center}{b}{:right}").unwrap();
+
+        assert_eq!(layout.tokens, vec![
+            Token::Block(String::from("a")),
+            Token::Align(Alignment::Center),
+            Token::Block(String::from("b")),
+            Token::Align(Alignment::Right),
+        ]);
+    }
+
+    #[test]
+    fn layouts_list_their_blocks() {
+        let layout = Layout::parse("{cpu} [{vol} │ ]{:right}{clock}").unwrap();
+        assert_eq!(layout.block_names(), vec!["cpu", "vol", "clock"]);
+    }
+
+    #[test]
+    fn invalid_layouts_are_rejected() {
+        assert_eq!(Layout::parse("{cpu"), Err(LayoutError::UnclosedBlock));
+        assert_eq!(Layout::parse("[{cpu}"), Err(LayoutError::UnclosedSection));
+        assert_eq!(
+            Layout::parse("{cpu}]"),
+            Err(LayoutError::UnexpectedChar(']'))
+        );
+        assert_eq!(
+            Layout::parse("{:middle}"),
+            Err(LayoutError::UnknownAlignment(String::from("middle")))
+        );
+        assert_eq!(
+            Layout::parse("[{:right}]"),
+            Err(LayoutError::NestedAlignment)
+        );
+    }
+}
@@ -1,35 +1,218 @@
 mod builder;
+mod control;
+mod layout;
+mod render;
+mod subscribe;
 
 use std::fmt;
 use std::time::{Duration, Instant};
 
 pub use builder::StatusBarBuilder;
-
-use super::statusblock::StatusBlock;
-use crate::monitor::{Command, MonitorReceiver};
+pub use layout::{Layout, LayoutError, Token};
+use render::Collapsed;
+pub use render::{Alignment, Sections, Segment, SegmentKind};
+use subscribe::Subscribers;
+
+use super::statusblock::{Blocks, Selector, StatusBlock};
+use crate::monitor::{Command, MonitorReceiver, Reply};
+use crate::output::{Frame, Output};
 use crate::threadpool::ThreadPool;
 
 /// Encapsulates a number of StatusBlocks.
 ///
 /// Contains information re. how StatusBlocks should be formatted, delimited,
 /// rendered, etc. as well as methods that operate across all blocks at once.
+///
+/// By default, blocks are drawn in order and separated by the delimiter. If a
+/// Layout is given, it will be used to arrange the blocks instead. In powerline
+/// mode, delimiters are styled to transition between the blocks' backgrounds.
+///
+/// If a max_width is set, blocks will be collapsed according to their priority
+/// whenever the bar grows too wide. Blocks with the lowest priority switch to
+/// their short text first, and if that isn't enough they're hidden entirely.
+/// Blocks further to the right go first when priorities are equal.
+///
+/// Blocks can be aligned to the left, center, or right sections of the bar.
+/// Outputs that support alignment will draw these separately, while others
+/// will draw them one after another, separated by the delimiter.
 pub struct StatusBar {
     pub blocks:             Vec<StatusBlock>,
     pub delimiter:          String,
     pub left_buffer:        String,
     pub right_buffer:       String,
     pub hide_empty_modules: bool,
+    pub layout:             Option<Layout>,
+    pub powerline:          bool,
+    pub max_width:          Option<usize>,
 }
 
 impl StatusBar {
-    pub fn update(&self, names: &[String]) {
-        self.blocks
+    /// Walks every block in the bar, including blocks inside of groups. See
+    /// Blocks.
+    pub fn walk(&self) -> Blocks<'_> { Blocks::new(&self.blocks) }
+
+    /// Returns the first block with the given name.
+    pub fn find(&self, name: &str) -> Option<&StatusBlock> {
+        self.walk().find(name)
+    }
+
+    /// Renders the current state of the StatusBar, for use by an Output.
+    pub fn frame(&self) -> Frame {
+        let sections = self.render(&self.collapse());
+
+        let mut segments = self.flatten(sections.clone());
+        if self.powerline {
+            render::draw_transitions(&mut segments);
+        }
+
+        Frame { segments, sections: self.align(sections) }
+    }
+
+    /// Renders the StatusBar into a list of Segments, with each section drawn
+    /// one after the other.
+    pub fn segments(&self) -> Vec<Segment> { self.frame().segments }
+
+    /// Renders the StatusBar into separately aligned sections. The left and
+    /// right buffers are drawn at the very start and end of the bar.
+    pub fn sections(&self) -> Sections { self.frame().sections }
+
+    /// Adds the left and right buffers to a set of rendered sections.
+    fn align(&self, mut sections: Sections) -> Sections {
+        sections.left.insert(0, Segment::text(&self.left_buffer));
+        sections.right.push(Segment::text(&self.right_buffer));
+
+        for alignment in render::ALIGNMENTS {
+            let segments = sections.get_mut(alignment);
+            render::remove_empty_text(segments);
+
+            if self.powerline {
+                render::draw_transitions(segments);
+            }
+        }
+
+        sections
+    }
+
+    /// Joins each non-empty section with the delimiter, and wraps the result
+    /// in the left and right buffers.
+    fn flatten(&self, sections: Sections) -> Vec<Segment> {
+        let mut segments = vec![Segment::text(&self.left_buffer)];
+
+        let sections = [sections.left, sections.center, sections.right];
+        let sections = sections.into_iter().filter(|s| !s.is_empty());
+
+        for (i, section) in sections.enumerate() {
+            if i > 0 {
+                segments.push(Segment::text(&self.delimiter));
+            }
+            segments.extend(section);
+        }
+
+        segments.push(Segment::text(&self.right_buffer));
+        render::remove_empty_text(&mut segments);
+        segments
+    }
+
+    /// Decides which blocks need to be collapsed for the bar to fit within its
+    /// max_width.
+    ///
+    /// Blocks are switched to their short text (starting with the lowest
+    /// priority) until the bar fits. If that isn't enough, the lowest-priority
+    /// blocks will be hidden entirely.
+    ///
+    /// Each block is only measured once (even if the Layout places it more
+    /// than once), and its width is taken off of the bar's as it's collapsed.
+    /// Hiding a block can also remove a delimiter, another copy of itself, or
+    /// a whole section of the Layout, so the estimate is never too narrow,
+    /// but it may collapse more than it needed to. Those blocks are brought
+    /// back afterwards, as long as the bar still fits.
+    fn collapse(&self) -> Collapsed<'_> {
+        let mut collapsed = Collapsed::default();
+
+        let max_width = match self.max_width {
+            Some(max_width) => max_width,
+            None => return collapsed,
+        };
+
+        let width = |collapsed: &Collapsed| {
+            render::width(&self.flatten(self.render(collapsed)))
+        };
+
+        let mut estimate = width(&collapsed);
+        if estimate <= max_width {
+            return collapsed;
+        }
+
+        let mut blocks = match &self.layout {
+            Some(layout) => render::layout_blocks(self, layout),
+            None => self.blocks.iter().collect(),
+        };
+
+        // Blocks on the right are collapsed first if priorities are equal.
+        blocks.reverse();
+        blocks.sort_by_key(|block| block.priority);
+
+        let mut widths: Vec<usize> = blocks
             .iter()
-            .filter(|block| block.name.is_some())
-            .filter(|block| names.contains(block.name.as_ref().unwrap()))
-            .for_each(|block| {
-                block.update_now();
-            })
+            .map(|block| block.to_string().chars().count())
+            .collect();
+
+        for (block, width) in blocks.iter().zip(&mut widths) {
+            if estimate <= max_width {
+                break;
+            }
+            if let Some(short_text) = block.short_text {
+                let short = short_text(&block.to_string()).chars().count();
+                estimate = estimate.saturating_sub(*width) + short;
+                *width = short;
+                collapsed.short.push(block);
+            }
+        }
+
+        for (block, width) in blocks.iter().zip(&widths) {
+            if estimate <= max_width {
+                break;
+            }
+            estimate = estimate.saturating_sub(*width);
+            collapsed.hidden.push(block);
+        }
+
+        // Undo the collapses that weren't needed, most recent first.
+        while let Some(block) = collapsed.hidden.pop() {
+            if width(&collapsed) > max_width {
+                collapsed.hidden.push(block);
+                return collapsed;
+            }
+        }
+        while let Some(block) = collapsed.short.pop() {
+            if width(&collapsed) > max_width {
+                collapsed.short.push(block);
+                return collapsed;
+            }
+        }
+
+        collapsed
+    }
+
+    fn render(&self, collapsed: &Collapsed) -> Sections {
+        match &self.layout {
+            Some(layout) =>
+                render::render_layout(self, &layout.tokens, collapsed),
+            None => render::render_blocks(self, collapsed),
+        }
+    }
+
+    /// Returns every block that matches one of the Selectors. Groups that
+    /// match are returned in place of their children.
+    pub fn select(&self, selectors: &[Selector]) -> Vec<&StatusBlock> {
+        self.walk().select(selectors)
+    }
+
+    /// Updates any blocks that match one of the Selectors.
+    pub fn update(&self, selectors: &[Selector]) {
+        for block in self.select(selectors) {
+            block.update_now();
+        }
     }
 
     pub fn attach_threadpool(&self, pool: &ThreadPool<String>) {
@@ -38,6 +221,13 @@ impl StatusBar {
         }
     }
 
+    /// Runs the hooks of any blocks that have changed since the last call.
+    pub fn run_hooks(&self) {
+        for block in &self.blocks {
+            block.run_hooks();
+        }
+    }
+
     pub fn time_until_next_update(&self) -> Option<Duration> {
         let now = Instant::now();
 
@@ -54,17 +244,30 @@ impl StatusBar {
         })
     }
 
-    pub fn run(self, draw_function: fn(&str), monitor_rx: MonitorReceiver) {
-        let mut bar = String::new();
+    pub fn run<O: Output>(self, mut output: O, monitor_rx: MonitorReceiver) {
+        let mut frame = Frame::default();
+        let mut subscribers = Subscribers::default();
 
         loop {
             // Update the bar, and draw it if necessary.
-            let new_bar = self.to_string();
-            if bar != new_bar {
-                bar = new_bar;
-                draw_function(&bar);
+            let new_frame = self.frame();
+            if frame != new_frame {
+                frame = new_frame;
+
+                if let Err(err) = output.draw(&frame) {
+                    eprintln!("Could not draw the bar: {}", err);
+                }
             }
 
+            // Blocks can change without changing the frame (eg. if they're
+            // collapsed, or left out of the Layout), so subscribers are
+            // checked either way.
+            subscribers.publish(&self, &frame);
+
+            // Let any hooks react to the new values. Each block runs its hooks
+            // on its own thread, so this won't block the loop.
+            self.run_hooks();
+
             let command: Option<Command> = {
                 // If there is an incoming command, return early.
                 if let Ok(command) = monitor_rx.try_recv() {
@@ -80,11 +283,30 @@ impl StatusBar {
                 }
             };
 
-            // Finally, respond to any external commands that came in.
-            match command {
-                Some(Command::Update(names)) => self.update(&names),
-                Some(Command::Shutdown) => break,
-                Some(Command::Refresh) | None => (),
+            // Finally, respond to any external commands that came in, replying
+            // to them if they were sent as Requests.
+            let (command, reply) = match command {
+                Some(Command::Request { command, reply }) =>
+                    (*command, Some(reply)),
+                Some(command) => (command, None),
+                None => continue,
+            };
+
+            // Subscribers are kept around, rather than being replied to once.
+            if let Command::Subscribe(kind) = command {
+                if let Some(reply) = reply {
+                    subscribers.add(kind, reply, &self, &frame);
+                    continue;
+                }
+            }
+
+            let result = self.execute(&command);
+            if let Some(reply) = reply {
+                let _ = reply.send(Reply::from(result));
+            }
+
+            if let Command::Shutdown = command {
+                break;
             }
         }
     }
@@ -92,32 +314,25 @@ impl StatusBar {
 
 impl fmt::Display for StatusBar {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut out = self.left_buffer.clone();
-
-        self.blocks.iter().enumerate().for_each(|(i, block)| {
-            let cache = block.to_string();
-
-            if cache.is_empty() && self.hide_empty_modules {
-                return;
-            }
-
-            if i > 0 && i < self.blocks.len() {
-                out.push_str(&self.delimiter);
-            }
-
-            out.push_str(&cache);
-        });
-        out.push_str(&self.right_buffer);
+        for segment in self.segments() {
+            write!(f, "{}", segment.text)?;
+        }
 
-        write!(f, "{}", out)
+        Ok(())
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use std::iter;
+    use std::sync::mpsc;
+    use std::thread;
+
     use super::*;
-    use crate::statusblock::StatusBlockBuilder;
+    use crate::monitor::Subscription;
+    use crate::statusblock::{BlockGroupBuilder, StatusBlockBuilder};
+    use crate::style::{Color, Style};
 
     #[test]
     fn default_has_correct_fields() {
@@ -126,6 +341,7 @@ mod tests {
         assert_eq!(bar.delimiter, "");
         assert_eq!(bar.left_buffer, "");
         assert_eq!(bar.right_buffer, "");
+        assert_eq!(bar.layout, None);
     }
 
     #[test]
@@ -199,4 +415,320 @@ mod tests {
 
         assert_eq!(bar.to_string(), " >>> test1 | test3 <<< ");
     }
+
+    #[test]
+    fn display_draws_groups_as_a_single_block() {
+        let cpu = StatusBlockBuilder::default()
+            .function(|| String::from("cpu"))
+            .build();
+        let iface = StatusBlockBuilder::default()
+            .function(|| String::from("wlan0"))
+            .build();
+        let speed = StatusBlockBuilder::default()
+            .function(|| String::from("↓12k"))
+            .build();
+        let net = BlockGroupBuilder::new(vec![iface, speed])
+            .delimiter(" ")
+            .left_buffer("NET [")
+            .right_buffer("]")
+            .build();
+
+        let bar =
+            StatusBarBuilder::new(vec![cpu, net]).delimiter(" | ").build();
+
+        assert_eq!(bar.to_string(), "cpu | NET [wlan0 ↓12k]");
+    }
+
+    #[test]
+    fn display_skips_delimiters_for_leading_empty_blocks() {
+        let block1 = StatusBlockBuilder::default().build();
+        let block2 = StatusBlockBuilder::default()
+            .function(|| String::from("test2"))
+            .build();
+
+        let bar = StatusBarBuilder::new(vec![block1, block2])
+            .delimiter(" | ")
+            .hide_empty_modules(true)
+            .build();
+
+        assert_eq!(bar.to_string(), "test2");
+    }
+
+    fn named_blocks() -> Vec<StatusBlock> {
+        let cpu = StatusBlockBuilder::default()
+            .name("cpu")
+            .function(|| String::from("cpu 5%"))
+            .build();
+        let vol = StatusBlockBuilder::default().name("vol").build();
+        let clock = StatusBlockBuilder::default()
+            .name("clock")
+            .function(|| String::from("12:00"))
+            .build();
+
+        vec![cpu, vol, clock]
+    }
+
+    #[test]
+    fn subscribers_see_changes_to_blocks_that_arent_drawn() {
+        let bar = StatusBarBuilder::new(named_blocks()).layout("{cpu}").build();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || bar.run(|_: &str| (), rx));
+
+        let (reply, replies) = mpsc::sync_channel(8);
+        let command = Box::new(Command::Subscribe(Subscription::Changes));
+        tx.send(Command::Request { command, reply }).unwrap();
+        tx.send(Command::Set {
+            selector: Selector::parse("vol"),
+            text:     String::from("50%"),
+            ttl:      None,
+        })
+        .unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let change = iter::from_fn(|| replies.recv_timeout(timeout).ok())
+            .map(|reply| reply.data)
+            .find(|data| data["name"] == "vol")
+            .unwrap();
+        assert_eq!(change["new_value"], "50%");
+
+        tx.send(Command::Shutdown).unwrap();
+    }
+
+    #[test]
+    fn display_follows_the_layout() {
+        let bar = StatusBarBuilder::new(named_blocks())
+            .layout("{clock} - {cpu}")
+            .left_buffer("> ")
+            .build();
+
+        assert_eq!(bar.to_string(), "> 12:00 - cpu 5%");
+    }
+
+    #[test]
+    fn display_hides_sections_with_hidden_blocks() {
+        let bar = StatusBarBuilder::new(named_blocks())
+            .layout("{cpu} │ [{vol} │ ]{clock}")
+            .build();
+
+        assert_eq!(bar.to_string(), "cpu 5% │ 12:00");
+    }
+
+    #[test]
+    fn layouts_cannot_refer_to_missing_blocks() {
+        let bar = StatusBarBuilder::new(named_blocks())
+            .layout("{cpu}[{missing}]")
+            .try_build();
+
+        let missing = String::from("missing");
+        assert_eq!(bar.err(), Some(LayoutError::UnknownBlock(missing)));
+    }
+
+    #[test]
+    fn invalid_templates_are_reported() {
+        let builder = StatusBarBuilder::new(named_blocks()).try_layout("{cpu");
+        assert_eq!(builder.err(), Some(LayoutError::UnclosedBlock));
+    }
+
+    #[test]
+    fn segments_keep_track_of_blocks() {
+        let bar = StatusBarBuilder::new(named_blocks())
+            .delimiter(" | ")
+            .hide_empty_modules(true)
+            .build();
+
+        let segments = bar.segments();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1], Segment::text(" | "));
+        assert_eq!(segments[2].kind, SegmentKind::Block {
+            name:  Some(String::from("clock")),
+            state: Default::default(),
+            value: Some(12.0),
+        });
+    }
+
+    #[test]
+    fn display_respects_block_separators() {
+        let block1 = StatusBlockBuilder::default()
+            .function(|| String::from("test1"))
+            .separator(" / ")
+            .build();
+        let block2 = StatusBlockBuilder::default()
+            .function(|| String::from("test2"))
+            .build();
+        let block3 = StatusBlockBuilder::default()
+            .function(|| String::from("test3"))
+            .build();
+
+        let bar = StatusBarBuilder::new(vec![block1, block2, block3])
+            .delimiter(" | ")
+            .build();
+
+        assert_eq!(bar.to_string(), "test1 / test2 | test3");
+    }
+
+    #[test]
+    fn powerline_transitions_use_block_backgrounds() {
+        let red = Color::rgb(255, 0, 0);
+        let blue = Color::rgb(0, 0, 255);
+
+        let block1 = StatusBlockBuilder::default()
+            .function(|| String::from("test1"))
+            .background(red)
+            .build();
+        let block2 = StatusBlockBuilder::default()
+            .function(|| String::from("test2"))
+            .background(blue)
+            .build();
+
+        let bar = StatusBarBuilder::new(vec![block1, block2])
+            .delimiter(">")
+            .right_buffer(">")
+            .powerline(true)
+            .build();
+
+        let segments = bar.segments();
+        assert_eq!(bar.to_string(), "test1>test2>");
+        assert_eq!(
+            segments[1].style,
+            Style::new().foreground(red).background(blue)
+        );
+        assert_eq!(segments[3].style, Style::new().foreground(blue));
+    }
+
+    fn prioritised_blocks() -> Vec<StatusBlock> {
+        let cpu = StatusBlockBuilder::default()
+            .name("cpu")
+            .function(|| String::from("cpu: 5%"))
+            .short_text(|text| text.replace("cpu: ", ""))
+            .priority(1)
+            .build();
+        let mem = StatusBlockBuilder::default()
+            .name("mem")
+            .function(|| String::from("mem: 2G"))
+            .short_text(|text| text.replace("mem: ", ""))
+            .build();
+        let clock = StatusBlockBuilder::default()
+            .name("clock")
+            .function(|| String::from("12:00"))
+            .priority(2)
+            .build();
+
+        vec![cpu, mem, clock]
+    }
+
+    #[test]
+    fn max_width_prefers_short_text() {
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .delimiter(" ")
+            .max_width(16)
+            .build();
+
+        assert_eq!(bar.to_string(), "cpu: 5% 2G 12:00");
+
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .delimiter(" ")
+            .max_width(11)
+            .build();
+
+        assert_eq!(bar.to_string(), "5% 2G 12:00");
+    }
+
+    #[test]
+    fn max_width_hides_low_priority_blocks() {
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .delimiter(" ")
+            .max_width(8)
+            .build();
+
+        assert_eq!(bar.to_string(), "5% 12:00");
+
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .layout("{cpu}[ | {mem}] | {clock}")
+            .max_width(10)
+            .build();
+
+        assert_eq!(bar.to_string(), "5% | 12:00");
+    }
+
+    #[test]
+    fn max_width_handles_blocks_placed_twice() {
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .layout("{cpu} {clock} {cpu}")
+            .max_width(13)
+            .build();
+
+        assert_eq!(bar.to_string(), "5% 12:00 5%");
+
+        let bar = StatusBarBuilder::new(prioritised_blocks())
+            .layout("{cpu} {clock} {cpu}")
+            .max_width(7)
+            .build();
+
+        assert_eq!(bar.to_string(), " 12:00 ");
+    }
+
+    #[test]
+    fn max_width_only_hides_what_it_needs_to() {
+        let a = StatusBlockBuilder::new(|| String::from("a")).build();
+        let b = StatusBlockBuilder::new(|| String::from("b")).build();
+        let c = StatusBlockBuilder::new(|| String::from("c")).build();
+        let bar = StatusBarBuilder::new(vec![a, b, c])
+            .delimiter(" | ")
+            .max_width(5)
+            .build();
+
+        assert_eq!(bar.to_string(), "a | b");
+    }
+
+    fn aligned_blocks() -> Vec<StatusBlock> {
+        let left = StatusBlockBuilder::default()
+            .function(|| String::from("left"))
+            .build();
+        let right1 = StatusBlockBuilder::default()
+            .function(|| String::from("right1"))
+            .align(Alignment::Right)
+            .build();
+        let center = StatusBlockBuilder::default()
+            .function(|| String::from("center"))
+            .align(Alignment::Center)
+            .build();
+        let right2 = StatusBlockBuilder::default()
+            .function(|| String::from("right2"))
+            .align(Alignment::Right)
+            .build();
+
+        vec![left, right1, center, right2]
+    }
+
+    #[test]
+    fn sections_are_delimited_separately() {
+        let bar = StatusBarBuilder::new(aligned_blocks())
+            .delimiter(" | ")
+            .left_buffer("[")
+            .right_buffer("]")
+            .build();
+
+        let text = |segments: &[Segment]| {
+            segments.iter().map(|s| s.text.clone()).collect::<String>()
+        };
+
+        let sections = bar.sections();
+        assert_eq!(text(&sections.left), "[left");
+        assert_eq!(text(&sections.center), "center");
+        assert_eq!(text(&sections.right), "right1 | right2]");
+
+        assert_eq!(bar.to_string(), "[left | center | right1 | right2]");
+    }
+
+    #[test]
+    fn layouts_can_change_sections() {
+        let bar = StatusBarBuilder::new(named_blocks())
+            .layout("{cpu}{:right}{clock}")
+            .build();
+
+        let sections = bar.sections();
+        assert_eq!(sections.left.len(), 1);
+        assert!(sections.center.is_empty());
+        assert_eq!(sections.right[0].text, "12:00");
+    }
 }
@@ -0,0 +1,324 @@
+use std::ptr;
+
+use serde::Serialize;
+
+use super::layout::{Layout, Token};
+use super::StatusBar;
+use crate::statusblock::{State, StatusBlock};
+use crate::style::Style;
+
+/// A single piece of a rendered StatusBar, such as a block or a delimiter.
+///
+/// Rendering a bar into Segments (rather than straight into a String) keeps
+/// track of where each block's output ended up, which lets output backends
+/// decorate them individually.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Segment {
+    pub text:       String,
+    #[serde(flatten)]
+    pub kind:       SegmentKind,
+    pub style:      Style,
+    /// Whether any markup in the text should be left for the output backend
+    /// to interpret, rather than being escaped.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub raw_markup: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SegmentKind {
+    /// Delimiters, buffers, and any other text that isn't part of a block.
+    #[default]
+    Text,
+    /// The output of a StatusBlock, along with its State and numeric value.
+    Block {
+        name:  Option<String>,
+        state: State,
+        value: Option<f64>,
+    },
+}
+
+/// Which section of the bar a block should be drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A StatusBar that has been rendered into separately aligned sections.
+///
+/// Each section is delimited on its own, so there are no delimiters between
+/// the end of one section and the start of the next.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Sections {
+    pub left:   Vec<Segment>,
+    pub center: Vec<Segment>,
+    pub right:  Vec<Segment>,
+}
+
+impl Sections {
+    pub fn get(&self, alignment: Alignment) -> &Vec<Segment> {
+        match alignment {
+            Alignment::Left => &self.left,
+            Alignment::Center => &self.center,
+            Alignment::Right => &self.right,
+        }
+    }
+
+    pub fn get_mut(&mut self, alignment: Alignment) -> &mut Vec<Segment> {
+        match alignment {
+            Alignment::Left => &mut self.left,
+            Alignment::Center => &mut self.center,
+            Alignment::Right => &mut self.right,
+        }
+    }
+
+    /// Iterates over each section, from left to right.
+    pub fn iter(&self) -> impl Iterator<Item = (Alignment, &Vec<Segment>)> {
+        ALIGNMENTS.into_iter().map(move |alignment| {
+            (alignment, self.get(alignment))
+        })
+    }
+}
+
+pub(super) const ALIGNMENTS: [Alignment; 3] =
+    [Alignment::Left, Alignment::Center, Alignment::Right];
+
+impl Segment {
+    pub fn text(text: &str) -> Self {
+        Self { text: String::from(text), ..Default::default() }
+    }
+
+    pub fn block(block: &StatusBlock) -> Self {
+        Self {
+            text: block.to_string(),
+            kind: SegmentKind::Block {
+                name:  block.name.clone(),
+                state: block.state(),
+                value: block.value(),
+            },
+            style: block.current_style(),
+            raw_markup: block.raw_markup,
+        }
+    }
+
+    pub fn is_block(&self) -> bool {
+        matches!(self.kind, SegmentKind::Block { .. })
+    }
+
+    /// Returns the name of the block that this was drawn from, if any.
+    pub fn name(&self) -> Option<&str> {
+        match &self.kind {
+            SegmentKind::Block { name, .. } => name.as_deref(),
+            SegmentKind::Text => None,
+        }
+    }
+
+    /// Returns the State of the block that this was drawn from, or State::Idle
+    /// if it isn't a block.
+    pub fn state(&self) -> State {
+        match &self.kind {
+            SegmentKind::Block { state, .. } => *state,
+            SegmentKind::Text => State::Idle,
+        }
+    }
+
+    /// Returns the numeric value of the block that this was drawn from.
+    pub fn value(&self) -> Option<f64> {
+        match &self.kind {
+            SegmentKind::Block { value, .. } => *value,
+            SegmentKind::Text => None,
+        }
+    }
+}
+
+/// Keeps track of the blocks that have been collapsed in order to fit the bar
+/// within its max_width.
+#[derive(Default)]
+pub(super) struct Collapsed<'a> {
+    pub short:  Vec<&'a StatusBlock>,
+    pub hidden: Vec<&'a StatusBlock>,
+}
+
+impl Collapsed<'_> {
+    fn is_short(&self, block: &StatusBlock) -> bool {
+        self.short.iter().any(|short| std::ptr::eq(*short, block))
+    }
+
+    fn is_hidden(&self, block: &StatusBlock) -> bool {
+        self.hidden.iter().any(|hidden| std::ptr::eq(*hidden, block))
+    }
+
+    /// Draws the given block, taking into account whether it's been collapsed.
+    /// Returns None if the block has been hidden.
+    fn draw(&self, block: &StatusBlock) -> Option<Segment> {
+        if self.is_hidden(block) {
+            return None;
+        }
+
+        let mut segment = Segment::block(block);
+        if let Some(short_text) = block.short_text {
+            if self.is_short(block) {
+                segment.text = short_text(&segment.text);
+            }
+        }
+
+        Some(segment)
+    }
+}
+
+/// Returns the number of characters in the rendered bar.
+pub(super) fn width(segments: &[Segment]) -> usize {
+    segments.iter().map(|segment| segment.text.chars().count()).sum()
+}
+
+/// Removes any segments that won't draw anything. Empty blocks are kept, as
+/// they've already been filtered according to hide_empty_modules.
+pub(super) fn remove_empty_text(segments: &mut Vec<Segment>) {
+    segments.retain(|segment| !segment.text.is_empty() || segment.is_block());
+}
+
+/// Draws each of the bar's blocks in order, in their own sections. Blocks are
+/// separated by their own separator if they have one, and by the bar's
+/// delimiter otherwise.
+pub(super) fn render_blocks(
+    bar: &StatusBar, collapsed: &Collapsed,
+) -> Sections {
+    let mut sections = Sections::default();
+
+    for alignment in ALIGNMENTS {
+        let blocks: Vec<(&StatusBlock, Segment)> = bar
+            .blocks
+            .iter()
+            .filter(|block| block.align == alignment)
+            .filter_map(|block| Some((block, collapsed.draw(block)?)))
+            .filter(|(_, segment)| {
+                !(segment.text.is_empty() && bar.hide_empty_modules)
+            })
+            .collect();
+
+        let segments = sections.get_mut(alignment);
+
+        for (i, (_, segment)) in blocks.iter().enumerate() {
+            if i > 0 {
+                let (previous, _) = blocks[i - 1];
+                let separator = previous.separator.as_ref();
+                let separator = separator.unwrap_or(&bar.delimiter);
+                segments.push(Segment::text(separator));
+            }
+            segments.push(segment.clone());
+        }
+    }
+
+    sections
+}
+
+/// Styles any text between blocks as a powerline-style transition, ie. using
+/// the background color of the block before it as the foreground, and the
+/// background color of the block after it as the background.
+///
+/// Text before the first block or after the last block is drawn in the color
+/// of the block next to it.
+pub(super) fn draw_transitions(segments: &mut [Segment]) {
+    let background = |segment: Option<&Segment>| {
+        segment.and_then(|segment| segment.style.background)
+    };
+
+    for i in 0..segments.len() {
+        if segments[i].is_block() {
+            continue;
+        }
+
+        let previous = segments[..i].iter().rev().find(|s| s.is_block());
+        let next = segments[i + 1..].iter().find(|s| s.is_block());
+
+        segments[i].style = match previous {
+            Some(_) => Style {
+                foreground: background(previous),
+                background: background(next),
+                ..Default::default()
+            },
+            None => Style {
+                foreground: background(next),
+                ..Default::default()
+            },
+        };
+    }
+}
+
+/// Draws the bar according to its Layout. Everything is drawn in the left
+/// section until the Layout says otherwise.
+pub(super) fn render_layout(
+    bar: &StatusBar, tokens: &[Token], collapsed: &Collapsed,
+) -> Sections {
+    let mut sections = Sections::default();
+    let mut alignment = Alignment::Left;
+
+    for token in tokens {
+        if let Token::Align(new_alignment) = token {
+            alignment = *new_alignment;
+            continue;
+        }
+
+        let token = std::slice::from_ref(token);
+        render_tokens(bar, token, collapsed, sections.get_mut(alignment));
+    }
+
+    sections
+}
+
+/// Renders the given tokens onto the end of `out`. Returns false if any of the
+/// blocks referred to by the tokens (not including those in nested sections)
+/// were hidden.
+fn render_tokens(
+    bar: &StatusBar, tokens: &[Token], collapsed: &Collapsed,
+    out: &mut Vec<Segment>,
+) -> bool {
+    let mut visible = true;
+
+    for token in tokens {
+        match token {
+            Token::Text(text) => out.push(Segment::text(text)),
+            Token::Block(name) => {
+                let segment =
+                    bar.find(name).and_then(|block| collapsed.draw(block));
+
+                match segment {
+                    Some(segment) if !segment.text.is_empty() =>
+                        out.push(segment),
+                    _ => visible = false,
+                }
+            },
+            Token::Section(tokens) => {
+                let mut section = Vec::new();
+                if render_tokens(bar, tokens, collapsed, &mut section) {
+                    out.append(&mut section);
+                }
+            },
+            Token::Align(_) => (),
+        }
+    }
+
+    visible
+}
+
+/// Returns every block that's drawn by the Layout, in the order that they're
+/// first placed. A block that's placed more than once is only returned once.
+pub(super) fn layout_blocks<'a>(
+    bar: &'a StatusBar, layout: &Layout,
+) -> Vec<&'a StatusBlock> {
+    let mut blocks: Vec<&StatusBlock> = Vec::new();
+
+    for name in layout.block_names() {
+        match bar.find(name) {
+            Some(block) if !blocks.iter().any(|b| ptr::eq(*b, block)) =>
+                blocks.push(block),
+            _ => (),
+        }
+    }
+
+    blocks
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use super::StatusBar;
+use crate::monitor::{Reply, ReplySender, Subscription};
+use crate::output::{Format, Frame, Json, Plain};
+use crate::statusblock::{Change, State};
+
+/// Everyone that's following the bar via the `subscribe` command.
+///
+/// Subscribers are sent a Reply for each new frame (or each block that
+/// changes, whether or not it's drawn). Sending never blocks, so any
+/// subscriber that falls too far behind is dropped, as is any subscriber whose
+/// connection has closed.
+#[derive(Default)]
+pub(super) struct Subscribers {
+    list: Vec<Subscriber>,
+}
+
+/// The text and State of each named block in the bar.
+type Snapshot = HashMap<String, (String, State)>;
+
+struct Subscriber {
+    kind:       Subscription,
+    tx:         ReplySender,
+    last_seen:  Snapshot,
+    last_drawn: Value,
+}
+
+impl Subscribers {
+    /// Adds a new subscriber, and immediately sends it the current frame.
+    pub fn add(
+        &mut self, kind: Subscription, tx: ReplySender, bar: &StatusBar,
+        frame: &Frame,
+    ) {
+        let mut subscriber = Subscriber {
+            kind,
+            tx,
+            last_seen: Snapshot::new(),
+            last_drawn: Value::Null,
+        };
+
+        let data = match kind {
+            Subscription::Changes => {
+                subscriber.last_seen = snapshot(bar);
+                Value::Null
+            },
+            kind => {
+                subscriber.last_drawn = draw(kind, frame);
+                subscriber.last_drawn.clone()
+            },
+        };
+
+        if subscriber.tx.try_send(Reply::ok(data)).is_ok() {
+            self.list.push(subscriber);
+        }
+    }
+
+    /// Sends the new frame (or any changes) to each subscriber. This should be
+    /// called whenever any block might have changed, even if the frame didn't,
+    /// and subscribers that have already seen it are skipped.
+    pub fn publish(&mut self, bar: &StatusBar, frame: &Frame) {
+        if self.list.is_empty() {
+            return;
+        }
+
+        // Only look at each block if someone's actually interested.
+        let mut current = Snapshot::new();
+        if self.list.iter().any(|s| s.kind == Subscription::Changes) {
+            current = snapshot(bar);
+        }
+
+        self.list.retain_mut(|subscriber| {
+            let replies = match subscriber.kind {
+                Subscription::Changes => {
+                    let changes = diff(&subscriber.last_seen, &current);
+                    subscriber.last_seen = current.clone();
+                    changes.iter().map(|change| json!(change)).collect()
+                },
+                kind => {
+                    // Frames can change without changing how they're drawn,
+                    // in which case there's nothing new to send.
+                    let drawn = draw(kind, frame);
+                    if drawn == subscriber.last_drawn {
+                        return true;
+                    }
+
+                    subscriber.last_drawn = drawn.clone();
+                    vec![drawn]
+                },
+            };
+
+            replies
+                .into_iter()
+                .all(|data| subscriber.tx.try_send(Reply::ok(data)).is_ok())
+        });
+    }
+}
+
+fn draw(kind: Subscription, frame: &Frame) -> Value {
+    match kind {
+        Subscription::Json => serde_json::from_str(&Json.format(frame))
+            .expect("Frames are always valid JSON"),
+        _ => json!(Plain.format(frame)),
+    }
+}
+
+/// Returns the text and State of every named block in the bar.
+fn snapshot(bar: &StatusBar) -> Snapshot {
+    bar.walk()
+        .filter_map(|block| {
+            let name = block.name.clone()?;
+            Some((name, (block.to_string(), block.state())))
+        })
+        .collect()
+}
+
+/// Describes each block that's different between the two snapshots, in order
+/// of name.
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<Change> {
+    let mut changes: Vec<Change> = new
+        .iter()
+        .filter(|(name, new)| old.get(*name) != Some(new))
+        .map(|(name, (new_value, new_state))| {
+            let (old_value, old_state) =
+                old.get(name).cloned().unwrap_or_default();
+
+            Change {
+                name: Some(name.clone()),
+                old_value,
+                new_value: new_value.clone(),
+                old_state,
+                new_state: *new_state,
+            }
+        })
+        .collect();
+
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::{StatusBarBuilder, StatusBlockBuilder};
+
+    fn bar() -> StatusBar {
+        let cpu = StatusBlockBuilder::default().name("cpu").build();
+        let mem = StatusBlockBuilder::default().name("mem").build();
+        StatusBarBuilder::new(vec![cpu, mem]).delimiter(" ").build()
+    }
+
+    #[test]
+    fn subscribers_are_sent_each_frame() {
+        let bar = bar();
+        let mut subscribers = Subscribers::default();
+
+        let (tx, rx) = mpsc::sync_channel(8);
+        subscribers.add(Subscription::Text, tx, &bar, &bar.frame());
+
+        subscribers.publish(&bar, &bar.frame());
+        bar.find("cpu").unwrap().set("5%", None);
+        subscribers.publish(&bar, &bar.frame());
+
+        assert_eq!(rx.try_recv().unwrap(), Reply::ok(json!(" ")));
+        assert_eq!(rx.try_recv().unwrap(), Reply::ok(json!("5% ")));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn subscribers_can_follow_block_changes() {
+        let bar = bar();
+        let mut subscribers = Subscribers::default();
+
+        let (tx, rx) = mpsc::sync_channel(8);
+        subscribers.add(Subscription::Changes, tx, &bar, &bar.frame());
+        assert_eq!(rx.try_recv().unwrap(), Reply::ok(Value::Null));
+
+        bar.find("mem").unwrap().set("1G", None);
+        subscribers.publish(&bar, &bar.frame());
+
+        let change = rx.try_recv().unwrap().data;
+        assert_eq!(change["name"], "mem");
+        assert_eq!(change["old_value"], "");
+        assert_eq!(change["new_value"], "1G");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn slow_subscribers_are_dropped() {
+        let bar = bar();
+        let mut subscribers = Subscribers::default();
+
+        let (tx, _rx) = mpsc::sync_channel(1);
+        subscribers.add(Subscription::Text, tx, &bar, &bar.frame());
+        assert_eq!(subscribers.list.len(), 1);
+
+        bar.find("cpu").unwrap().set("5%", None);
+        subscribers.publish(&bar, &bar.frame());
+        assert!(subscribers.list.is_empty());
+    }
+}
@@ -1,15 +1,31 @@
+use std::ops::RangeBounds;
 use std::sync::Mutex;
 use std::time::Duration;
 
-use super::{StatusBlock, TimedCache};
+use super::{BlockGroup, Hook, State, StatusBlock, Threshold, TimedCache};
+use crate::statusbar::Alignment;
+use crate::style::{Color, Style};
 
 #[derive(Default)]
 pub struct StatusBlockBuilder {
     pub name:            Option<String>,
+    pub tags:            Vec<String>,
     pub min_size:        Option<usize>,
     pub max_size:        Option<usize>,
     pub function:        Option<fn() -> String>,
     pub update_interval: Option<Duration>,
+    pub thresholds:      Vec<Threshold>,
+    pub parser:          Option<fn(&str) -> Option<f64>>,
+    pub on_change:       Vec<Hook>,
+    pub on_state_change: Vec<Hook>,
+    pub style:           Style,
+    pub state_styles:    Vec<(State, Style)>,
+    pub separator:       Option<String>,
+    pub priority:        i32,
+    pub short_text:      Option<fn(&str) -> String>,
+    pub align:           Alignment,
+    pub raw_markup:      bool,
+    pub signal:          Option<i32>,
 }
 
 #[allow(dead_code)]
@@ -25,6 +41,13 @@ impl StatusBlockBuilder {
         self
     }
 
+    /// Adds the block to the given tag, so that it can be selected alongside
+    /// other blocks with `tag:NAME`.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(String::from(tag));
+        self
+    }
+
     pub fn min_size(mut self, min_size: usize) -> Self {
         self.min_size = Some(min_size);
         self
@@ -50,6 +73,99 @@ impl StatusBlockBuilder {
         self.update_interval = Some(interval);
         self
     }
+
+    /// Assigns the given State to the block whenever its value falls within
+    /// range. Thresholds are checked in the order that they were added.
+    pub fn threshold<R: RangeBounds<f64>>(
+        mut self, state: State, range: R,
+    ) -> Self {
+        self.thresholds.push(Threshold::new(state, range));
+        self
+    }
+
+    /// Overrides the function used to read a numeric value from the block's
+    /// output. By default, the first number in the output is used.
+    pub fn parser(mut self, parser: fn(&str) -> Option<f64>) -> Self {
+        self.parser = Some(parser);
+        self
+    }
+
+    /// Runs the given hook whenever the block's output changes, and once at
+    /// startup. See StatusBlock::run_hooks.
+    pub fn on_change(mut self, hook: Hook) -> Self {
+        self.on_change.push(hook);
+        self
+    }
+
+    /// Runs the given hook whenever the block crosses into a new State.
+    pub fn on_state_change(mut self, hook: Hook) -> Self {
+        self.on_state_change.push(hook);
+        self
+    }
+
+    pub fn foreground(mut self, color: Color) -> Self {
+        self.style.foreground = Some(color);
+        self
+    }
+
+    pub fn background(mut self, color: Color) -> Self {
+        self.style.background = Some(color);
+        self
+    }
+
+    /// Replaces the block's Style entirely.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Patches the block's Style whenever it's in the given State, eg. to turn
+    /// it red when it becomes Critical.
+    pub fn state_style(mut self, state: State, style: Style) -> Self {
+        self.state_styles.push((state, style));
+        self
+    }
+
+    /// Draws the given separator between this block and the next one, in
+    /// place of the bar's delimiter.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = Some(String::from(separator));
+        self
+    }
+
+    /// Blocks with a lower priority will be collapsed first if the bar grows
+    /// too wide. The default is 0.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets a function to shorten the block's output, which will be used if
+    /// the bar grows too wide.
+    pub fn short_text(mut self, f: fn(&str) -> String) -> Self {
+        self.short_text = Some(f);
+        self
+    }
+
+    /// Chooses which section of the bar the block should be drawn in.
+    pub fn align(mut self, align: Alignment) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Lets any markup in the block's output (eg. lemonbar's `%{..}` or Pango
+    /// tags) through to the output backend, rather than escaping it.
+    pub fn raw_markup(mut self, raw_markup: bool) -> Self {
+        self.raw_markup = raw_markup;
+        self
+    }
+
+    /// Updates the block whenever the bar receives the given signal, eg.
+    /// `signals::rtmin(10)` for `pkill -RTMIN+10`, or `signals::SIGUSR1`.
+    pub fn signal(mut self, signal: i32) -> Self {
+        self.signal = Some(signal);
+        self
+    }
 }
 
 impl From<StatusBlockBuilder> for StatusBlock {
@@ -57,13 +173,110 @@ impl From<StatusBlockBuilder> for StatusBlock {
         let function = builder.function.unwrap_or(String::new);
 
         Self {
-            name:     builder.name,
+            name: builder.name,
+            tags: builder.tags,
             min_size: builder.min_size,
             max_size: builder.max_size,
-            cache:    Mutex::new(TimedCache::new(
+            thresholds: builder.thresholds,
+            parser: builder.parser,
+            on_change: builder.on_change,
+            on_state_change: builder.on_state_change,
+            style: builder.style,
+            state_styles: builder.state_styles,
+            separator: builder.separator,
+            priority: builder.priority,
+            short_text: builder.short_text,
+            align: builder.align,
+            raw_markup: builder.raw_markup,
+            signal: builder.signal,
+            cache: Mutex::new(TimedCache::new(
                 builder.update_interval,
                 function,
             )),
+            ..Default::default()
+        }
+    }
+}
+
+/// Builds a StatusBlock that draws a group of other StatusBlocks.
+#[derive(Default)]
+pub struct BlockGroupBuilder {
+    pub name:               Option<String>,
+    pub tags:               Vec<String>,
+    pub signal:             Option<i32>,
+    pub blocks:             Vec<StatusBlock>,
+    pub delimiter:          String,
+    pub left_buffer:        String,
+    pub right_buffer:       String,
+    pub hide_empty_modules: bool,
+}
+
+#[allow(dead_code)]
+impl BlockGroupBuilder {
+    pub fn new(blocks: Vec<StatusBlock>) -> Self {
+        Self { blocks, ..Default::default() }
+    }
+
+    pub fn build(self) -> StatusBlock { self.into() }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Adds the group to the given tag. See StatusBlockBuilder::tag.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(String::from(tag));
+        self
+    }
+
+    /// Updates every block in the group whenever the bar receives the given
+    /// signal. See StatusBlockBuilder::signal.
+    pub fn signal(mut self, signal: i32) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+
+    pub fn blocks(mut self, blocks: Vec<StatusBlock>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: &str) -> Self {
+        self.delimiter = String::from(delimiter);
+        self
+    }
+
+    pub fn left_buffer(mut self, left_buffer: &str) -> Self {
+        self.left_buffer = String::from(left_buffer);
+        self
+    }
+
+    pub fn right_buffer(mut self, right_buffer: &str) -> Self {
+        self.right_buffer = String::from(right_buffer);
+        self
+    }
+
+    pub fn hide_empty_modules(mut self, hide_empty_modules: bool) -> Self {
+        self.hide_empty_modules = hide_empty_modules;
+        self
+    }
+}
+
+impl From<BlockGroupBuilder> for StatusBlock {
+    fn from(builder: BlockGroupBuilder) -> Self {
+        Self {
+            name: builder.name,
+            tags: builder.tags,
+            signal: builder.signal,
+            group: Some(BlockGroup {
+                blocks:             builder.blocks,
+                delimiter:          builder.delimiter,
+                left_buffer:        builder.left_buffer,
+                right_buffer:       builder.right_buffer,
+                hide_empty_modules: builder.hide_empty_modules,
+            }),
+            ..Default::default()
         }
     }
 }
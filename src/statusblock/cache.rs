@@ -1,23 +1,32 @@
-use std::sync::mpsc;
+use std::panic;
+use std::sync::mpsc::{self, TryRecvError};
 use std::time::{Duration, Instant};
 
 use crate::threadpool::{
-    JobPacket, JobsSender, Message, ResultsReceiver, ResultsSender, ThreadPool,
+    JobPacket, JobsSender, Message, ResultsReceiver, ThreadPool,
 };
 
 /// A self-updating cache on a timer. Will probably need to be broken down.
 ///
 /// TimedCaches will try to evaluate in the background if a JobsSender has been
-/// provided via attach_threadpool(). Otherwise, they'll evaluate locally.
+/// provided via attach_threadpool(). Otherwise, they'll evaluate locally. Each
+/// job gets its own results channel, which is dropped by the pool if the job
+/// panics.
+///
+/// If the function panics, the old value is kept and the error is counted.
+/// Paused caches won't update on their timer, but can still be updated
+/// manually. Values can also be held for a while, in which case the cache
+/// won't update until they expire.
 pub struct TimedCache<T> {
     value:           T,
     function:        fn() -> T,
     last_update:     Option<Instant>,
     update_interval: Option<Duration>,
     jobs_tx:         Option<JobsSender<T>>,
-    results_tx:      Option<ResultsSender<T>>,
     results_rx:      Option<ResultsReceiver<T>>,
-    waiting:         bool,
+    errors:          usize,
+    paused:          bool,
+    held_until:      Option<Instant>,
 }
 
 impl<T: Default> TimedCache<T> {
@@ -28,9 +37,10 @@ impl<T: Default> TimedCache<T> {
             last_update: None,
             update_interval,
             jobs_tx: None,
-            results_tx: None,
             results_rx: None,
-            waiting: false,
+            errors: 0,
+            paused: false,
+            held_until: None,
         }
     }
 }
@@ -46,14 +56,21 @@ impl<T> TimedCache<T> {
             last_update: None,
             update_interval,
             jobs_tx: None,
-            results_tx: None,
             results_rx: None,
-            waiting: false,
+            errors: 0,
+            paused: false,
+            held_until: None,
         }
     }
 
     pub fn next_update(&self) -> Option<Instant> {
-        if !self.waiting {
+        if self.is_waiting() || self.paused {
+            None
+        }
+        else if self.held_until.is_some() {
+            self.held_until
+        }
+        else {
             match (self.update_interval, self.last_update) {
                 (Some(interval), Some(last_update)) =>
                     Some(last_update + interval),
@@ -61,9 +78,6 @@ impl<T> TimedCache<T> {
                 (_, None) => Some(Instant::now()),
             }
         }
-        else {
-            None
-        }
     }
 
     pub fn get(&mut self) -> &T {
@@ -77,14 +91,26 @@ impl<T> TimedCache<T> {
     }
 
     pub fn update(&mut self) {
-        if self.results_rx.is_some() && self.waiting {
-            let packet = self.results_rx.as_ref().unwrap().try_recv();
+        if let Some(results_rx) = &self.results_rx {
+            let result = match results_rx.try_recv() {
+                Ok(packet) => Some(packet.result),
+                Err(TryRecvError::Disconnected) => None,
+                Err(TryRecvError::Empty) => return,
+            };
+
+            self.results_rx = None;
+            self.evaluated(result);
+            return;
+        }
 
-            if let Ok(packet) = packet {
-                self.overwrite(packet.result);
-                self.waiting = false;
-            }
+        if self.paused {
+            return;
+        }
 
+        if let Some(held_until) = self.held_until {
+            if Instant::now() >= held_until {
+                self.update_now();
+            }
             return;
         }
 
@@ -105,17 +131,20 @@ impl<T> TimedCache<T> {
     }
 
     pub fn update_now(&mut self) {
+        self.held_until = None;
+
         match &self.jobs_tx {
             // If there's no threadpool, update now.
             None => {
-                let value = (self.function)();
-                self.overwrite(value)
+                let result = panic::catch_unwind(self.function);
+                self.evaluated(result.ok())
             },
             // Otherwise, create a job.
             Some(tx) => {
+                let (results_tx, results_rx) = mpsc::sync_channel(1);
                 let job = JobPacket {
                     job:       self.function,
-                    return_tx: self.results_tx.as_ref().unwrap().clone(),
+                    return_tx: results_tx,
                 };
 
                 // Try to send the job as a message
@@ -125,29 +154,71 @@ impl<T> TimedCache<T> {
                 // single-threaded mode.
                 if result.is_err() {
                     self.jobs_tx = None;
-                    self.results_tx = None;
                     self.results_rx = None;
                 }
                 else {
-                    self.waiting = true;
+                    self.results_rx = Some(results_rx);
                 }
             },
         }
     }
 
     pub fn attach_threadpool(&mut self, pool: &ThreadPool<T>) {
-        let jobs_tx = pool.jobs_tx.clone();
-        let (results_tx, results_rx) = mpsc::sync_channel(1);
-
-        self.jobs_tx = Some(jobs_tx);
-        self.results_tx = Some(results_tx);
-        self.results_rx = Some(results_rx);
+        self.jobs_tx = Some(pool.jobs_tx.clone());
     }
 
+    /// Overwrites the value until the next update. Any update that's already
+    /// in progress is thrown away.
     pub fn overwrite(&mut self, value: T) {
         self.value = value;
         self.last_update = Some(Instant::now());
+        self.results_rx = None;
     }
+
+    /// Overwrites the value, and keeps it until the given time has passed (or
+    /// until the cache is updated manually).
+    pub fn hold(&mut self, value: T, ttl: Duration) {
+        self.overwrite(value);
+        self.held_until = Some(Instant::now() + ttl);
+    }
+
+    /// Stores the result of evaluating the function. If it panicked (ie. there
+    /// is no result), the old value is kept until the next update.
+    fn evaluated(&mut self, result: Option<T>) {
+        match result {
+            Some(value) => {
+                self.value = value;
+                self.last_update = Some(Instant::now());
+            },
+            None => {
+                self.errors += 1;
+                self.last_update = Some(Instant::now());
+            },
+        }
+    }
+
+    /// Stops the cache from updating on its timer.
+    pub fn pause(&mut self) { self.paused = true; }
+
+    pub fn resume(&mut self) { self.paused = false; }
+
+    pub fn set_update_interval(&mut self, interval: Option<Duration>) {
+        self.update_interval = interval;
+    }
+
+    pub fn update_interval(&self) -> Option<Duration> { self.update_interval }
+
+    pub fn last_update(&self) -> Option<Instant> { self.last_update }
+
+    /// Whether the cache is waiting for a threadpool to evaluate it.
+    pub fn is_waiting(&self) -> bool { self.results_rx.is_some() }
+
+    /// The number of times that the function has panicked.
+    pub fn errors(&self) -> usize { self.errors }
+
+    pub fn is_paused(&self) -> bool { self.paused }
+
+    pub fn held_until(&self) -> Option<Instant> { self.held_until }
 }
 
 impl<T: Clone + Default> Default for TimedCache<T> {
@@ -158,9 +229,10 @@ impl<T: Clone + Default> Default for TimedCache<T> {
             last_update:     None,
             update_interval: None,
             jobs_tx:         None,
-            results_tx:      None,
             results_rx:      None,
-            waiting:         false,
+            errors:          0,
+            paused:          false,
+            held_until:      None,
         }
     }
 }
@@ -211,6 +283,92 @@ mod tests {
         assert_ne!(first_value, *cache.get());
     }
 
+    #[test]
+    fn cache_counts_errors_and_keeps_the_old_value() {
+        let mut cache = TimedCache::with_initial_value(1, None, || panic!());
+
+        assert_eq!(*cache.get(), 1);
+        assert_eq!(cache.errors(), 1);
+        assert!(cache.last_update().is_some());
+    }
+
+    #[test]
+    fn cache_counts_errors_from_the_threadpool() {
+        let (monitor_tx, _monitor_rx) = mpsc::sync_channel(10);
+        let pool = ThreadPool::new(1, monitor_tx);
+
+        let mut cache = TimedCache::with_initial_value(1, None, || panic!());
+        cache.attach_threadpool(&pool);
+
+        cache.update_now();
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(*cache.get(), 1);
+        assert_eq!(cache.errors(), 1);
+        assert!(!cache.is_waiting());
+    }
+
+    #[test]
+    fn held_values_expire() {
+        let interval = Some(Duration::from_millis(1));
+        let mut cache = TimedCache::with_initial_value(0, interval, || 1);
+
+        cache.hold(2, Duration::from_millis(50));
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(*cache.get(), 2);
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(*cache.get(), 1);
+    }
+
+    #[test]
+    fn paused_caches_wait_until_resumed() {
+        let interval = Some(Duration::from_millis(1));
+        let mut cache = TimedCache::with_initial_value(
+            Instant::now(),
+            interval,
+            Instant::now,
+        );
+
+        let first_value = *cache.get();
+        cache.pause();
+        assert_eq!(cache.next_update(), None);
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(first_value, *cache.get());
+
+        cache.resume();
+        assert_ne!(first_value, *cache.get());
+    }
+
+    #[test]
+    fn overwritten_values_beat_pending_updates() {
+        let (monitor_tx, _monitor_rx) = mpsc::sync_channel(10);
+        let pool = ThreadPool::new(1, monitor_tx);
+
+        let mut cache = TimedCache::with_initial_value(0, None, || 1);
+        cache.attach_threadpool(&pool);
+
+        cache.update_now();
+        cache.overwrite(2);
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(*cache.get(), 2);
+        assert_eq!(*cache.get(), 2);
+    }
+
+    #[test]
+    fn paused_caches_keep_expired_values() {
+        let mut cache = TimedCache::with_initial_value(0, None, || 1);
+
+        cache.hold(2, Duration::from_millis(1));
+        cache.pause();
+        assert_eq!(cache.next_update(), None);
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(*cache.get(), 2);
+    }
+
     #[test]
     fn cache_does_not_update_when_interval_is_none() {
         let mut cache =
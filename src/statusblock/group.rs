@@ -0,0 +1,283 @@
+use std::fmt;
+use std::slice;
+use std::time::{Duration, Instant};
+
+use super::{Selector, State, StatusBlock};
+use crate::threadpool::ThreadPool;
+
+/// A number of StatusBlocks that are drawn as a single block.
+///
+/// Groups have their own delimiter and buffers, so related blocks can be kept
+/// together under one label, eg. "NET [wlan0 ↓12k ↑3k]". They're attached
+/// to a regular StatusBlock, and so can be placed anywhere that a block can.
+#[derive(Default)]
+pub struct BlockGroup {
+    pub blocks:             Vec<StatusBlock>,
+    pub delimiter:          String,
+    pub left_buffer:        String,
+    pub right_buffer:       String,
+    pub hide_empty_modules: bool,
+}
+
+/// Walks a list of StatusBlocks depth-first, visiting each block before any
+/// blocks inside of its group.
+pub struct Blocks<'a> {
+    stack:    Vec<slice::Iter<'a, StatusBlock>>,
+    children: Option<&'a BlockGroup>,
+}
+
+impl<'a> Blocks<'a> {
+    pub fn new(blocks: &'a [StatusBlock]) -> Self {
+        Self { stack: vec![blocks.iter()], children: None }
+    }
+
+    /// Skips the blocks inside of the group that was just visited, if any.
+    pub fn skip_children(&mut self) { self.children = None; }
+
+    /// Returns the first block with the given name.
+    pub fn find(mut self, name: &str) -> Option<&'a StatusBlock> {
+        Iterator::find(&mut self, |block| block.name.as_deref() == Some(name))
+    }
+
+    /// Returns every block that matches one of the Selectors. Groups that
+    /// match are returned in place of their children.
+    pub fn select(mut self, selectors: &[Selector]) -> Vec<&'a StatusBlock> {
+        let mut out = Vec::new();
+
+        while let Some(block) = self.next() {
+            if selectors.iter().any(|selector| selector.matches(block)) {
+                out.push(block);
+                self.skip_children();
+            }
+        }
+
+        out
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = &'a StatusBlock;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(group) = self.children.take() {
+            self.stack.push(group.blocks.iter());
+        }
+
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(block) => {
+                    self.children = block.group.as_ref();
+                    return Some(block);
+                },
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+}
+
+impl BlockGroup {
+    /// Walks the group's children, and their children in turn.
+    pub fn walk(&self) -> Blocks<'_> { Blocks::new(&self.blocks) }
+
+    /// Returns the first child block with the given name.
+    pub fn find(&self, name: &str) -> Option<&StatusBlock> {
+        self.walk().find(name)
+    }
+
+    pub fn update_now(&self) {
+        for block in &self.blocks {
+            block.update_now();
+        }
+    }
+
+    pub fn pause(&self) {
+        for block in &self.blocks {
+            block.pause();
+        }
+    }
+
+    pub fn resume(&self) {
+        for block in &self.blocks {
+            block.resume();
+        }
+    }
+
+    pub fn set_update_interval(&self, interval: Option<Duration>) {
+        for block in &self.blocks {
+            block.set_update_interval(interval);
+        }
+    }
+
+    pub fn attach_threadpool(&self, pool: &ThreadPool<String>) {
+        for block in &self.blocks {
+            block.attach_threadpool(pool);
+        }
+    }
+
+    pub fn next_update(&self) -> Option<Instant> {
+        self.blocks.iter().filter_map(|block| block.next_update()).min()
+    }
+
+    /// Returns the most severe State out of all the child blocks.
+    pub fn state(&self) -> State {
+        self.blocks.iter().map(|block| block.state()).max().unwrap_or_default()
+    }
+
+    pub fn run_hooks(&self) {
+        for block in &self.blocks {
+            block.run_hooks();
+        }
+    }
+}
+
+impl fmt::Display for BlockGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let blocks = self.blocks.iter().map(|block| block.to_string());
+
+        let out = join(
+            blocks,
+            &self.delimiter,
+            &self.left_buffer,
+            &self.right_buffer,
+            self.hide_empty_modules,
+        );
+
+        write!(f, "{}", out)
+    }
+}
+
+/// Joins a number of rendered blocks with the given delimiter and buffers,
+/// optionally skipping any blocks that are empty.
+fn join<I: Iterator<Item = String>>(
+    blocks: I, delimiter: &str, left_buffer: &str, right_buffer: &str,
+    hide_empty_modules: bool,
+) -> String {
+    let mut out = String::from(left_buffer);
+    let mut first = true;
+
+    for block in blocks {
+        if block.is_empty() && hide_empty_modules {
+            continue;
+        }
+
+        if !first {
+            out.push_str(delimiter);
+        }
+
+        out.push_str(&block);
+        first = false;
+    }
+
+    out.push_str(right_buffer);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statusblock::BlockGroupBuilder;
+    use crate::StatusBlockBuilder;
+
+    fn net() -> StatusBlock {
+        let iface = StatusBlockBuilder::default()
+            .name("iface")
+            .function(|| String::from("wlan0"))
+            .build();
+        let down = StatusBlockBuilder::default()
+            .name("down")
+            .function(|| String::from("↓12k"))
+            .build();
+        let up = StatusBlockBuilder::default()
+            .name("up")
+            .function(|| String::from("↑3k"))
+            .build();
+
+        BlockGroupBuilder::new(vec![iface, down, up])
+            .name("net")
+            .delimiter(" ")
+            .left_buffer("NET [")
+            .right_buffer("]")
+            .build()
+    }
+
+    #[test]
+    fn groups_draw_their_children() {
+        assert_eq!(net().to_string(), "NET [wlan0 ↓12k ↑3k]");
+    }
+
+    #[test]
+    fn groups_are_truncated_by_character() {
+        let mut group = net();
+
+        group.max_size = Some(12);
+        assert_eq!(group.to_string(), "NET [wlan0 ↓");
+
+        group.max_size = None;
+        group.min_size = Some(22);
+        assert_eq!(group.to_string(), "NET [wlan0 ↓12k ↑3k]  ");
+    }
+
+    #[test]
+    fn groups_can_find_their_children() {
+        let group = net();
+        let inner = group.group.as_ref().unwrap();
+
+        assert_eq!(inner.find("down").unwrap().to_string(), "↓12k");
+        assert!(inner.find("net").is_none());
+    }
+
+    #[test]
+    fn walks_visit_groups_before_their_children() {
+        let clock = StatusBlockBuilder::default().name("clock").build();
+        let bar = BlockGroupBuilder::new(vec![net(), clock]).build();
+        let group = bar.group.as_ref().unwrap();
+
+        let names: Vec<_> =
+            group.walk().map(|block| block.name.as_deref()).collect();
+        assert_eq!(names, [
+            Some("net"),
+            Some("iface"),
+            Some("down"),
+            Some("up"),
+            Some("clock"),
+        ]);
+
+        let mut blocks = group.walk();
+        assert_eq!(blocks.next().unwrap().name.as_deref(), Some("net"));
+        blocks.skip_children();
+        assert_eq!(blocks.next().unwrap().name.as_deref(), Some("clock"));
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn groups_hide_empty_children_if_needed() {
+        let empty = StatusBlockBuilder::default().build();
+        let full = StatusBlockBuilder::default()
+            .function(|| String::from("full"))
+            .build();
+
+        let group = BlockGroupBuilder::new(vec![empty, full])
+            .delimiter(",")
+            .hide_empty_modules(true)
+            .build();
+
+        assert_eq!(group.to_string(), "full");
+    }
+
+    #[test]
+    fn groups_take_the_most_severe_state() {
+        let warning = StatusBlockBuilder::default()
+            .function(|| String::from("85"))
+            .threshold(State::Warning, 80.0..)
+            .build();
+        let critical = StatusBlockBuilder::default()
+            .function(|| String::from("5"))
+            .threshold(State::Critical, ..10.0)
+            .build();
+
+        let group = BlockGroupBuilder::new(vec![warning, critical]).build();
+        assert_eq!(group.state(), State::Critical);
+    }
+}
@@ -0,0 +1,46 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+
+use serde::Serialize;
+
+use super::State;
+
+/// A function that is run in response to a StatusBlock changing.
+pub type Hook = fn(&Change);
+
+/// The hooks to run for a single Change.
+pub(super) type HookJob = (Vec<Hook>, Change);
+
+/// Starts a thread that runs hooks one at a time, in the order that they're
+/// sent. A hook that panics doesn't stop the ones after it.
+pub(super) fn spawn_runner() -> mpsc::Sender<HookJob> {
+    let (tx, rx) = mpsc::channel::<HookJob>();
+
+    thread::spawn(move || {
+        for (hooks, change) in rx {
+            for hook in hooks {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(&change)));
+            }
+        }
+    });
+
+    tx
+}
+
+/// Describes a change in a StatusBlock's output. Passed to any hooks that fire
+/// in response to that change.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Change {
+    pub name:      Option<String>,
+    pub old_value: String,
+    pub new_value: String,
+    pub old_state: State,
+    pub new_state: State,
+}
+
+impl Change {
+    pub fn value_changed(&self) -> bool { self.old_value != self.new_value }
+
+    pub fn state_changed(&self) -> bool { self.old_state != self.new_state }
+}
@@ -0,0 +1,88 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use super::{State, StatusBlock};
+
+/// A snapshot of a StatusBlock and its cache, eg. for answering an `inspect`
+/// command. Times are given in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlockInfo {
+    pub name:        Option<String>,
+    pub tags:        Vec<String>,
+    pub text:        String,
+    pub state:       State,
+    pub value:       Option<f64>,
+    /// How often the block is updated, if it's on a timer.
+    pub interval:    Option<f64>,
+    /// How long ago the block was last updated.
+    pub last_update: Option<f64>,
+    /// How long until the block's next update, if one is scheduled.
+    pub next_update: Option<f64>,
+    /// Whether the block is waiting for a threadpool to update it.
+    pub waiting:     bool,
+    /// How many times the block's function has panicked.
+    pub errors:      usize,
+    pub paused:      bool,
+    /// How long until text that was set externally expires.
+    pub held:        Option<f64>,
+}
+
+impl StatusBlock {
+    pub fn info(&self) -> BlockInfo {
+        let now = Instant::now();
+
+        let text = self.to_string();
+        let state = self.state();
+        let value = self.value();
+        let next_update = self.next_update();
+
+        let cache = self.cache.lock().unwrap();
+
+        BlockInfo {
+            name: self.name.clone(),
+            tags: self.tags.clone(),
+            text,
+            state,
+            value,
+            interval: cache.update_interval().map(|i| i.as_secs_f64()),
+            last_update: cache
+                .last_update()
+                .map(|instant| (now - instant).as_secs_f64()),
+            next_update: next_update.map(|instant| {
+                instant.saturating_duration_since(now).as_secs_f64()
+            }),
+            waiting: cache.is_waiting(),
+            errors: cache.errors(),
+            paused: cache.is_paused(),
+            held: cache.held_until().map(|instant| {
+                instant.saturating_duration_since(now).as_secs_f64()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn info_reports_the_cache() {
+        let interval = Some(Duration::from_secs(5));
+        let mut block = StatusBlock::new(|| String::from("50%"), interval);
+        block.name = Some(String::from("cpu"));
+
+        let info = block.info();
+
+        assert_eq!(info.name.as_deref(), Some("cpu"));
+        assert_eq!(info.text, "50%");
+        assert_eq!(info.value, Some(50.0));
+        assert_eq!(info.interval, Some(5.0));
+        assert!(info.last_update.unwrap() < 1.0);
+        assert!(info.next_update.unwrap() > 4.0);
+        assert_eq!((info.waiting, info.errors, info.paused), (false, 0, false));
+        assert_eq!(info.held, None);
+    }
+}
@@ -1,13 +1,26 @@
 mod builder;
 mod cache;
+mod group;
+mod hooks;
+mod info;
+mod selector;
+mod threshold;
 
 use std::fmt;
-use std::sync::Mutex;
+use std::sync::{mpsc, Mutex};
 use std::time::{Duration, Instant};
 
-pub use builder::StatusBlockBuilder;
+pub use builder::{BlockGroupBuilder, StatusBlockBuilder};
 use cache::TimedCache;
+pub use group::{BlockGroup, Blocks};
+use hooks::HookJob;
+pub use hooks::{Change, Hook};
+pub use info::BlockInfo;
+pub use selector::Selector;
+pub use threshold::{parse_number, State, Threshold};
 
+use crate::statusbar::Alignment;
+use crate::style::Style;
 use crate::threadpool::ThreadPool;
 
 /// Encapsulates a fn() -> String.
@@ -17,10 +30,42 @@ use crate::threadpool::ThreadPool;
 /// do so automatically with or without access to a threadpool.
 #[derive(Default)]
 pub struct StatusBlock {
-    pub name:     Option<String>,
-    pub min_size: Option<usize>,
-    pub max_size: Option<usize>,
-    cache:        Mutex<TimedCache<String>>,
+    pub name:            Option<String>,
+    /// Named sets (eg. "net") that commands can select the block by. See
+    /// Selector.
+    pub tags:            Vec<String>,
+    pub min_size:        Option<usize>,
+    pub max_size:        Option<usize>,
+    /// Decide the block's State, using its output parsed as a number.
+    pub thresholds:      Vec<Threshold>,
+    /// Reads the number from the block's output. Defaults to parse_number.
+    pub parser:          Option<fn(&str) -> Option<f64>>,
+    /// Run whenever the block's output changes. See run_hooks.
+    pub on_change:       Vec<Hook>,
+    /// Run whenever the block moves into a new State.
+    pub on_state_change: Vec<Hook>,
+    /// Drawn in place of the block's own output, if set.
+    pub group:           Option<BlockGroup>,
+    /// Patched with the style for the block's current State, if it has one.
+    pub style:           Style,
+    pub state_styles:    Vec<(State, Style)>,
+    /// Drawn between this block and the next, in place of the bar's
+    /// delimiter.
+    pub separator:       Option<String>,
+    /// Blocks with a lower priority are collapsed first when the bar runs out
+    /// of space, either by switching to their short text or by being hidden.
+    pub priority:        i32,
+    pub short_text:      Option<fn(&str) -> String>,
+    /// The section of the bar to draw the block in, unless the bar has a
+    /// Layout.
+    pub align:           Alignment,
+    /// Passes markup in the output through to the backend, unescaped.
+    pub raw_markup:      bool,
+    /// Updates the block whenever the bar receives it. See the signals module.
+    pub signal:          Option<i32>,
+    cache:               Mutex<TimedCache<String>>,
+    last_seen:           Mutex<(String, State)>,
+    hooks_tx:            Mutex<Option<mpsc::Sender<HookJob>>>,
 }
 
 impl StatusBlock {
@@ -32,11 +77,19 @@ impl StatusBlock {
     }
 
     pub fn next_update(&self) -> Option<Instant> {
+        if let Some(group) = &self.group {
+            return group.next_update();
+        }
+
         let cache = self.cache.lock().unwrap();
         cache.next_update()
     }
 
     pub fn attach_threadpool(&self, pool: &ThreadPool<String>) {
+        if let Some(group) = &self.group {
+            group.attach_threadpool(pool);
+        }
+
         let mut cache = self.cache.lock().unwrap();
         cache.attach_threadpool(pool);
     }
@@ -50,8 +103,13 @@ impl StatusBlock {
         self.truncate_cache()
     }
 
-    /// Updates the StatusBlock immediately, ignoring the timer.
+    /// Updates the StatusBlock immediately, ignoring the timer. Groups will
+    /// update all of their children.
     pub fn update_now(&self) {
+        if let Some(group) = &self.group {
+            group.update_now();
+        }
+
         let mut cache = self.cache.lock().unwrap();
         cache.update_now();
 
@@ -59,16 +117,175 @@ impl StatusBlock {
         self.truncate_cache()
     }
 
+    /// Stops the StatusBlock from updating on its timer, until it's resumed.
+    /// Groups will pause all of their children.
+    pub fn pause(&self) {
+        if let Some(group) = &self.group {
+            group.pause();
+        }
+
+        self.cache.lock().unwrap().pause();
+    }
+
+    /// Restarts the StatusBlock's timer after it's been paused. Groups will
+    /// resume all of their children.
+    pub fn resume(&self) {
+        if let Some(group) = &self.group {
+            group.resume();
+        }
+
+        self.cache.lock().unwrap().resume();
+    }
+
+    /// Changes how often the StatusBlock updates, or stops it from updating
+    /// on a timer if the interval is None. Groups will change the interval of
+    /// all of their children.
+    pub fn set_update_interval(&self, interval: Option<Duration>) {
+        if let Some(group) = &self.group {
+            group.set_update_interval(interval);
+        }
+
+        self.cache.lock().unwrap().set_update_interval(interval);
+    }
+
+    /// Overwrites the StatusBlock's output with the given text. If a TTL is
+    /// given, the text is kept until it expires. Otherwise, it's kept until
+    /// the block's next update.
+    pub fn set(&self, text: &str, ttl: Option<Duration>) {
+        let mut cache = self.cache.lock().unwrap();
+        match ttl {
+            Some(ttl) => cache.hold(String::from(text), ttl),
+            None => cache.overwrite(String::from(text)),
+        }
+
+        drop(cache);
+        self.truncate_cache()
+    }
+
+    /// Returns the numeric value of the StatusBlock, as read by its parser.
+    pub fn value(&self) -> Option<f64> {
+        let parser = self.parser.unwrap_or(parse_number);
+
+        if let Some(group) = &self.group {
+            return parser(&group.to_string());
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        parser(cache.get())
+    }
+
+    /// Returns the State of the first Threshold that contains the StatusBlock's
+    /// value, or State::Idle if there isn't one. Groups without any Thresholds
+    /// take on the most severe State of their children.
+    pub fn state(&self) -> State {
+        if let Some(group) = &self.group {
+            if self.thresholds.is_empty() {
+                return group.state();
+            }
+        }
+
+        match self.value() {
+            Some(value) => self
+                .thresholds
+                .iter()
+                .find(|threshold| threshold.contains(value))
+                .map(|threshold| threshold.state)
+                .unwrap_or_default(),
+            None => State::Idle,
+        }
+    }
+
+    /// Returns the Style that the block should currently be drawn with, taking
+    /// into account its State.
+    pub fn current_style(&self) -> Style {
+        let state = self.state();
+
+        self.state_styles
+            .iter()
+            .filter(|(s, _)| *s == state)
+            .fold(self.style, |style, (_, patch)| style.patch(*patch))
+    }
+
+    /// Compares the StatusBlock's output and State to the last time that this
+    /// method was called, and returns a Change if either of them are different.
+    pub fn poll_changes(&self) -> Option<Change> {
+        let new_value = self.to_string();
+        let new_state = self.state();
+
+        let mut last_seen = self.last_seen.lock().unwrap();
+        if *last_seen == (new_value.clone(), new_state) {
+            return None;
+        }
+
+        let (old_value, old_state) = std::mem::replace(
+            &mut *last_seen,
+            (new_value.clone(), new_state),
+        );
+
+        Some(Change {
+            name: self.name.clone(),
+            old_value,
+            new_value,
+            old_state,
+            new_state,
+        })
+    }
+
+    /// Checks for changes, and hands any relevant hooks to the block's own
+    /// thread so that they don't hold up the caller. Hooks run one at a time,
+    /// in the order that the changes happened. Returns true if any were
+    /// queued.
+    ///
+    /// The first check counts as a change from an empty, Idle block, so hooks
+    /// are run once at startup with the block's initial output and State.
+    pub fn run_hooks(&self) -> bool {
+        if let Some(group) = &self.group {
+            group.run_hooks();
+        }
+
+        if self.on_change.is_empty() && self.on_state_change.is_empty() {
+            return false;
+        }
+
+        let change = match self.poll_changes() {
+            Some(change) => change,
+            None => return false,
+        };
+
+        let mut hooks: Vec<Hook> = Vec::new();
+        if change.value_changed() {
+            hooks.extend(&self.on_change);
+        }
+        if change.state_changed() {
+            hooks.extend(&self.on_state_change);
+        }
+
+        if hooks.is_empty() {
+            return false;
+        }
+
+        let mut hooks_tx = self.hooks_tx.lock().unwrap();
+        let tx = hooks_tx.get_or_insert_with(hooks::spawn_runner);
+        tx.send((hooks, change)).is_ok()
+    }
+
     fn truncate_cache(&self) {
         let mut cache = self.cache.lock().unwrap();
-        let inner = cache.get_mut();
+        self.fit_size(cache.get_mut());
+    }
 
+    /// Truncates or pads the text to fit between min_size and max_size. Sizes
+    /// are counted in characters rather than bytes.
+    fn fit_size(&self, text: &mut String) {
         if let Some(max) = self.max_size {
-            inner.truncate(max);
+            if let Some((end, _)) = text.char_indices().nth(max) {
+                text.truncate(end);
+            }
         }
         if let Some(min) = self.min_size {
-            if inner.len() < min {
-                inner.push_str(&" ".repeat(min - inner.len()))
+            let len = text.chars().count();
+            if len < min {
+                text.push_str(&" ".repeat(min - len))
             }
         }
     }
@@ -79,16 +296,13 @@ impl fmt::Display for StatusBlock {
         let mut cache = self.cache.lock().unwrap();
         let out = cache.get_mut();
 
-        // TODO: Find out why this needs to be *here* and not in truncate_cache
-        if let Some(max) = self.max_size {
-            out.truncate(max);
-        }
-        if let Some(min) = self.min_size {
-            if out.len() < min {
-                out.push_str(&" ".repeat(min - out.len()))
-            }
+        if let Some(group) = &self.group {
+            *out = group.to_string();
         }
 
+        // TODO: Find out why this needs to be *here* and not in truncate_cache
+        self.fit_size(out);
+
         write!(f, "{}", out)
     }
 }
@@ -103,6 +317,7 @@ mod tests {
         assert_eq!(block.name, None);
         assert_eq!(block.min_size, None);
         assert_eq!(block.max_size, None);
+        assert!(block.thresholds.is_empty());
         assert_eq!(block.to_string(), "");
     }
 
@@ -133,4 +348,146 @@ mod tests {
         block.update_now();
         assert_eq!(block.to_string(), "a short string      ");
     }
+
+    #[test]
+    fn sizes_count_characters() {
+        let mut block = StatusBlock::new(|| String::from("21°C"), None);
+
+        block.max_size = Some(3);
+        block.update_now();
+        assert_eq!(block.to_string(), "21°");
+
+        block.max_size = None;
+        block.min_size = Some(6);
+        block.update_now();
+        assert_eq!(block.to_string(), "21°C  ");
+    }
+
+    #[test]
+    fn state_uses_the_first_matching_threshold() {
+        let mut block = StatusBlock::new(|| String::from("bat: 12%"), None);
+
+        block.thresholds = vec![
+            Threshold::new(State::Critical, ..15.0),
+            Threshold::new(State::Warning, ..30.0),
+        ];
+
+        assert_eq!(block.value(), Some(12.0));
+        assert_eq!(block.state(), State::Critical);
+    }
+
+    #[test]
+    fn state_is_idle_without_a_value() {
+        let mut block = StatusBlock::new(|| String::from("unplugged"), None);
+        block.thresholds = vec![Threshold::new(State::Critical, ..)];
+
+        assert_eq!(block.state(), State::Idle);
+    }
+
+    #[test]
+    fn state_styles_patch_the_style() {
+        use crate::style::Color;
+
+        let red = Color::rgb(255, 0, 0);
+        let mut block = StatusBlock::new(|| String::from("bat: 12%"), None);
+
+        block.style = Style::new().bold();
+        block.thresholds = vec![Threshold::new(State::Critical, ..15.0)];
+        block.state_styles = vec![
+            (State::Critical, Style::new().background(red)),
+            (State::Good, Style::new().underline()),
+        ];
+
+        assert_eq!(block.current_style(), Style::new().bold().background(red));
+    }
+
+    #[test]
+    fn custom_parsers_are_used() {
+        let mut block = StatusBlock::new(|| String::from("up"), None);
+
+        block.parser = Some(|text| match text {
+            "up" => Some(1.0),
+            _ => Some(0.0),
+        });
+        block.thresholds = vec![
+            Threshold::new(State::Good, 1.0..),
+            Threshold::new(State::Critical, ..1.0),
+        ];
+
+        assert_eq!(block.state(), State::Good);
+    }
+
+    #[test]
+    fn poll_changes_reports_old_and_new_values() {
+        let mut block = StatusBlock::new(|| String::from("bat: 10%"), None);
+        block.name = Some(String::from("battery"));
+        block.thresholds = vec![Threshold::new(State::Critical, ..15.0)];
+
+        let change = block.poll_changes().unwrap();
+        assert_eq!(change.name.as_deref(), Some("battery"));
+        assert_eq!(change.old_value, "");
+        assert_eq!(change.new_value, "bat: 10%");
+        assert_eq!(change.old_state, State::Idle);
+        assert_eq!(change.new_state, State::Critical);
+
+        assert_eq!(block.poll_changes(), None);
+    }
+
+    #[test]
+    fn hooks_fire_on_the_right_changes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static VALUE_CHANGES: AtomicUsize = AtomicUsize::new(0);
+        static STATE_CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+        let mut block = StatusBlock::new(|| String::from("vpn: up"), None);
+        block.on_change = vec![|_| {
+            VALUE_CHANGES.fetch_add(1, Ordering::SeqCst);
+        }];
+        block.on_state_change = vec![|_| {
+            STATE_CHANGES.fetch_add(1, Ordering::SeqCst);
+        }];
+
+        // The value changes, but there are no thresholds to change the state.
+        assert!(block.run_hooks());
+        wait_until(|| VALUE_CHANGES.load(Ordering::SeqCst) == 1);
+        assert_eq!(STATE_CHANGES.load(Ordering::SeqCst), 0);
+
+        // Nothing has changed since the last check.
+        assert!(!block.run_hooks());
+    }
+
+    /// Waits (for up to a second) for the hooks to catch up.
+    fn wait_until(done: impl Fn() -> bool) {
+        let start = std::time::Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(1), "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn hooks_run_at_startup_and_in_order() {
+        static SEEN: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+        let mut block = StatusBlock::new(|| String::from("0"), None);
+        block.on_change = vec![|change| {
+            // Give later changes a chance to overtake this one.
+            std::thread::sleep(Duration::from_millis(5));
+            let values = (change.old_value.clone(), change.new_value.clone());
+            SEEN.lock().unwrap().push(values);
+        }];
+
+        block.run_hooks();
+        for text in ["1", "2", "3"] {
+            block.set(text, None);
+            block.run_hooks();
+        }
+
+        wait_until(|| SEEN.lock().unwrap().len() == 4);
+        let seen = SEEN.lock().unwrap();
+        let seen: Vec<(&str, &str)> =
+            seen.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+        assert_eq!(seen, [("", "0"), ("0", "1"), ("1", "2"), ("2", "3")]);
+    }
 }
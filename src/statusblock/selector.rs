@@ -0,0 +1,155 @@
+use std::fmt;
+
+use super::StatusBlock;
+
+/// Picks out blocks for commands like `update` and `set`.
+///
+/// Selectors are written as either:
+///  - `tag:net`, which matches every block tagged with "net",
+///  - `cpu*`, a glob where `*` matches any run of characters and `?` matches
+///    exactly one,
+///  - `cpu`, which only matches the block with that exact name, or
+///  - `name:cpu*`, which matches the block named "cpu*", for names that would
+///    otherwise be read as a glob or tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selector {
+    Name(String),
+    Glob(String),
+    Tag(String),
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Self {
+        if let Some(name) = selector.strip_prefix("name:") {
+            Selector::Name(String::from(name))
+        }
+        else if let Some(tag) = selector.strip_prefix("tag:") {
+            Selector::Tag(String::from(tag))
+        }
+        else if selector.contains(['*', '?']) {
+            Selector::Glob(String::from(selector))
+        }
+        else {
+            Selector::Name(String::from(selector))
+        }
+    }
+
+    pub fn matches(&self, block: &StatusBlock) -> bool {
+        match (self, &block.name) {
+            (Selector::Tag(tag), _) => block.tags.contains(tag),
+            (Selector::Name(name), Some(block_name)) => name == block_name,
+            (Selector::Glob(glob), Some(block_name)) => {
+                let glob: Vec<char> = glob.chars().collect();
+                let name: Vec<char> = block_name.chars().collect();
+                glob_matches(&glob, &name)
+            },
+            (_, None) => false,
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Name(name) if Selector::parse(name) != *self =>
+                write!(f, "name:{}", name),
+            Selector::Name(name) | Selector::Glob(name) =>
+                write!(f, "{}", name),
+            Selector::Tag(tag) => write!(f, "tag:{}", tag),
+        }
+    }
+}
+
+/// Checks the whole of the text against a glob, backtracking to the last `*`
+/// whenever the two stop lining up.
+fn glob_matches(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                last_star = Some((g, t));
+                g += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            },
+            _ => match last_star {
+                // Let the last star swallow one more character, and retry.
+                Some((star, star_t)) => {
+                    last_star = Some((star, star_t + 1));
+                    g = star + 1;
+                    t = star_t + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StatusBlockBuilder;
+
+    #[test]
+    fn selectors_are_parsed_by_prefix_and_wildcards() {
+        let name = |name: &str| String::from(name);
+
+        assert_eq!(Selector::parse("cpu"), Selector::Name(name("cpu")));
+        assert_eq!(Selector::parse("cpu*"), Selector::Glob(name("cpu*")));
+        assert_eq!(Selector::parse("c?u"), Selector::Glob(name("c?u")));
+        assert_eq!(Selector::parse("tag:net"), Selector::Tag(name("net")));
+        assert_eq!(Selector::parse("tag:net").to_string(), "tag:net");
+    }
+
+    #[test]
+    fn names_can_be_escaped() {
+        let name = |name: &str| Selector::Name(String::from(name));
+
+        assert_eq!(Selector::parse("name:cpu*"), name("cpu*"));
+        assert_eq!(Selector::parse("name:tag:x"), name("tag:x"));
+        assert_eq!(Selector::parse("name:cpu"), name("cpu"));
+
+        assert_eq!(name("cpu*").to_string(), "name:cpu*");
+        assert_eq!(name("tag:x").to_string(), "name:tag:x");
+        assert_eq!(name("name:x").to_string(), "name:name:x");
+        assert_eq!(name("cpu").to_string(), "cpu");
+    }
+
+    #[test]
+    fn globs_match_whole_names() {
+        let matches = |glob: &str, text: &str| {
+            let glob: Vec<char> = glob.chars().collect();
+            let text: Vec<char> = text.chars().collect();
+            glob_matches(&glob, &text)
+        };
+
+        assert!(matches("cpu*", "cpu0"));
+        assert!(matches("cpu*", "cpu"));
+        assert!(matches("*u*", "cpu0"));
+        assert!(matches("c?u?", "cpu0"));
+        assert!(matches("*a*b", "xaab-ab"));
+        assert!(!matches("cpu?", "cpu"));
+        assert!(!matches("cpu", "cpu0"));
+        assert!(!matches("*b", "abc"));
+    }
+
+    #[test]
+    fn tags_and_names_match_blocks() {
+        let block = StatusBlockBuilder::default()
+            .name("wlan0")
+            .tag("net")
+            .build();
+
+        assert!(Selector::parse("tag:net").matches(&block));
+        assert!(Selector::parse("wlan?").matches(&block));
+        assert!(Selector::parse("wlan0").matches(&block));
+        assert!(!Selector::parse("tag:audio").matches(&block));
+        assert!(!Selector::parse("wlan").matches(&block));
+    }
+}
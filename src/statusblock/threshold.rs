@@ -0,0 +1,123 @@
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use serde::Serialize;
+
+/// The state of a StatusBlock, as decided by its thresholds.
+///
+/// Output backends can map these onto colors or markup, and hooks can react
+/// when a block moves from one state to another. States are ordered by
+/// severity, from Idle to Critical.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+    Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    #[default]
+    Idle,
+    Info,
+    Good,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            State::Idle => "idle",
+            State::Info => "info",
+            State::Good => "good",
+            State::Warning => "warning",
+            State::Critical => "critical",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Assigns a State to any value that falls within a range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Threshold {
+    pub state: State,
+    pub start: Bound<f64>,
+    pub end:   Bound<f64>,
+}
+
+impl Threshold {
+    pub fn new<R: RangeBounds<f64>>(state: State, range: R) -> Self {
+        Self {
+            state,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        (self.start, self.end).contains(&value)
+    }
+}
+
+/// The default value parser for StatusBlocks. Returns the first number that
+/// appears in the given text, if there is one.
+///
+/// eg. "bat: -12.5%" -> Some(-12.5)
+pub fn parse_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+
+    // Include a leading minus sign, if there is one.
+    let start = if text[..start].ends_with('-') {
+        start - 1
+    }
+    else {
+        start
+    };
+
+    // Take digits and at most one decimal point.
+    let mut seen_point = false;
+    let end = text[start + 1..]
+        .find(|c: char| {
+            if c == '.' && !seen_point {
+                seen_point = true;
+                return false;
+            }
+            !c.is_ascii_digit()
+        })
+        .map(|i| i + start + 1)
+        .unwrap_or(text.len());
+
+    text[start..end].trim_end_matches('.').parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_number_finds_the_first_number() {
+        assert_eq!(parse_number("bat: 14%"), Some(14.0));
+        assert_eq!(parse_number("cpu 93.5% (4 cores)"), Some(93.5));
+        assert_eq!(parse_number("temp -3.25C"), Some(-3.25));
+        assert_eq!(parse_number("version 1.2.3"), Some(1.2));
+        assert_eq!(parse_number("total: 12."), Some(12.0));
+        assert_eq!(parse_number("no numbers here"), None);
+    }
+
+    #[test]
+    fn thresholds_respect_their_bounds() {
+        let low = Threshold::new(State::Critical, ..15.0);
+        assert!(low.contains(14.9));
+        assert!(!low.contains(15.0));
+
+        let high = Threshold::new(State::Warning, 80.0..=90.0);
+        assert!(high.contains(80.0));
+        assert!(high.contains(90.0));
+        assert!(!high.contains(90.1));
+    }
+
+    #[test]
+    fn states_display_in_lowercase() {
+        assert_eq!(State::Idle.to_string(), "idle");
+        assert_eq!(State::Critical.to_string(), "critical");
+    }
+}
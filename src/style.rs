@@ -0,0 +1,137 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+/// An RGB color. Parsed from and displayed as "#rrggbb".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Describes how a block (or any other part of a bar) should be decorated.
+///
+/// It's up to each output backend to decide how (or whether) to draw these.
+/// Plain-text backends ignore them entirely. Urgent blocks are those that need
+/// the user's attention, which some backends (eg. i3bar) highlight specially.
+///
+/// eg. `Style::new().foreground(Color::rgb(255, 0, 0)).bold()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold:       bool,
+    pub underline:  bool,
+    pub urgent:     bool,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self { Self { r, g, b } }
+}
+
+impl Style {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn foreground(mut self, color: Color) -> Self {
+        self.foreground = Some(color);
+        self
+    }
+
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn urgent(mut self) -> Self {
+        self.urgent = true;
+        self
+    }
+
+    /// Draws `other` on top of this Style. Colors are replaced if `other` sets
+    /// them, and any other attributes are added.
+    pub fn patch(self, other: Style) -> Self {
+        Self {
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            bold:       self.bold || other.bold,
+            underline:  self.underline || other.underline,
+            urgent:     self.urgent || other.urgent,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid color {:?} (expected #rrggbb)", s));
+        }
+
+        let channel = |i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Self::rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(
+        &self, serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_can_be_parsed() {
+        assert_eq!("#282c34".parse(), Ok(Color::rgb(0x28, 0x2c, 0x34)));
+        assert_eq!("FF0000".parse(), Ok(Color::rgb(255, 0, 0)));
+        assert!("#fff".parse::<Color>().is_err());
+        assert!("#gggggg".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn styles_can_be_patched() {
+        let red = Color::rgb(255, 0, 0);
+        let blue = Color::rgb(0, 0, 255);
+
+        let base = Style::new().foreground(red).background(red).bold();
+        let patch = Style::new().background(blue).urgent();
+
+        assert_eq!(base.patch(patch), Style {
+            foreground: Some(red),
+            background: Some(blue),
+            bold: true,
+            urgent: true,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn colors_display_as_hex() {
+        assert_eq!(Color::rgb(40, 44, 52).to_string(), "#282c34");
+    }
+}
@@ -0,0 +1,38 @@
+//! Helpers that are shared between the tests of several modules.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::output::Frame;
+use crate::statusbar::{Segment, SegmentKind};
+use crate::State;
+
+/// Returns a Frame that only contains the given text.
+pub fn frame(text: &str) -> Frame {
+    Frame { segments: vec![Segment::text(text)], ..Default::default() }
+}
+
+/// Returns a Segment as it would be drawn for a block, without needing to set
+/// up a StatusBlock first.
+pub fn block_segment(
+    name: &str, text: &str, state: State, value: Option<f64>,
+) -> Segment {
+    Segment {
+        text: String::from(text),
+        kind: SegmentKind::Block {
+            name: Some(String::from(name)),
+            state,
+            value,
+        },
+        ..Default::default()
+    }
+}
+
+/// Returns a path in a temporary directory that's only used by this process.
+/// The directory is created, but the path itself is left alone.
+pub fn temp_path(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("abar-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
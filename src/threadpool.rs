@@ -1,3 +1,4 @@
+use std::panic;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
@@ -78,7 +79,13 @@ impl Worker {
             match message {
                 Message::Terminate => break,
                 Message::Job(JobPacket { job, return_tx }) => {
-                    return_tx.send(ResultPacket { result: job() }).unwrap();
+                    // If the job panics, return_tx is dropped without a
+                    // result, rather than taking the worker down with it.
+                    // Whoever sent the job may have stopped waiting for it,
+                    // in which case the result is thrown away.
+                    if let Ok(result) = panic::catch_unwind(job) {
+                        let _ = return_tx.send(ResultPacket { result });
+                    }
                     monitor_tx.send(Command::Refresh).unwrap();
                 },
             }
@@ -142,4 +149,23 @@ mod tests {
         jobs_tx.send(Message::Terminate).unwrap();
         worker.handle.unwrap().join().unwrap();
     }
+
+    #[test]
+    fn workers_survive_panicking_jobs() {
+        let (jobs_tx, jobs_rx) = mpsc::sync_channel(10);
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+
+        let (monitor_tx, _monitor_rx) = mpsc::sync_channel(10);
+        let worker = Worker::new(jobs_rx, monitor_tx);
+
+        let job: fn() -> String = || panic!("oh no");
+        for _ in 0..2 {
+            let (return_tx, results_rx) = mpsc::sync_channel(1);
+            jobs_tx.send(Message::Job(JobPacket { job, return_tx })).unwrap();
+            assert!(results_rx.recv().is_err());
+        }
+
+        jobs_tx.send(Message::Terminate).unwrap();
+        worker.handle.unwrap().join().unwrap();
+    }
 }